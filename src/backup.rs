@@ -0,0 +1,182 @@
+//! Backup registers and backup SRAM
+//!
+//! The 20 RTC backup registers and, on devices that have it, the 4 KB backup SRAM keep their
+//! contents through resets and, with a battery on VBAT, while VDD is off. The backup registers
+//! are also kept in Standby, the backup SRAM only if the backup regulator is enabled.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{backup::BackupDomain, pwr::PwrExt, stm32};
+//!
+//! const DFU_MAGIC: u32 = 0xDF00_B007;
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let mut pwr = dp.PWR.constrain();
+//! let mut backup = BackupDomain::take(&mut pwr).unwrap();
+//!
+//! if backup.registers.read(0) == DFU_MAGIC {
+//!     backup.registers.write(0, 0);
+//!     // jump to the bootloader
+//! }
+//! ```
+
+use crate::pwr::Pwr;
+use crate::stm32::RTC;
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+use crate::{rcc::Enable, stm32::PWR};
+
+static mut TAKEN: bool = false;
+
+/// Backup domain storage, split into its parts so they can be handed out separately
+pub struct BackupDomain {
+    pub registers: BackupRegisters,
+    #[cfg(any(
+        feature = "stm32f405",
+        feature = "stm32f407",
+        feature = "stm32f415",
+        feature = "stm32f417",
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    pub sram: BackupSram,
+}
+
+impl BackupDomain {
+    /// Enables write access to the backup domain and returns its storage, only once
+    pub fn take(pwr: &mut Pwr) -> Option<Self> {
+        cortex_m::interrupt::free(|_| {
+            if unsafe { TAKEN } {
+                return None;
+            }
+            unsafe { TAKEN = true };
+
+            pwr.enable_backup_access();
+
+            Some(BackupDomain {
+                registers: BackupRegisters { _0: () },
+                #[cfg(any(
+                    feature = "stm32f405",
+                    feature = "stm32f407",
+                    feature = "stm32f415",
+                    feature = "stm32f417",
+                    feature = "stm32f427",
+                    feature = "stm32f429",
+                    feature = "stm32f437",
+                    feature = "stm32f439",
+                    feature = "stm32f446",
+                    feature = "stm32f469",
+                    feature = "stm32f479"
+                ))]
+                sram: BackupSram { _0: () },
+            })
+        })
+    }
+}
+
+/// RTC backup registers, 20 words
+///
+/// The registers are cleared by a backup domain reset, which happens when the RTC clock source
+/// is changed, and by a tamper event.
+pub struct BackupRegisters {
+    _0: (),
+}
+
+impl BackupRegisters {
+    /// Number of backup registers
+    pub const COUNT: usize = 20;
+
+    /// Reads backup register `index`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below `COUNT`.
+    pub fn read(&self, index: usize) -> u32 {
+        unsafe { (*RTC::ptr()).bkpr[index].read().bits() }
+    }
+
+    /// Writes backup register `index`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below `COUNT`.
+    pub fn write(&mut self, index: usize, value: u32) {
+        unsafe { (*RTC::ptr()).bkpr[index].write(|w| w.bits(value)) };
+    }
+}
+
+/// 4 KB backup SRAM
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+pub struct BackupSram {
+    _0: (),
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+impl BackupSram {
+    /// Size of the backup SRAM in bytes
+    pub const SIZE: usize = 4096;
+    const ADDRESS: usize = 0x4002_4000;
+
+    /// Enables the backup SRAM clock and returns its contents.
+    ///
+    /// With `retain`, the backup regulator is started so the contents are also kept in Standby
+    /// and on VBAT. It stays on, even after a reset, until it is disabled by
+    /// [`disable_retention`](#method.disable_retention) or VBAT is lost.
+    pub fn enable(self, retain: bool) -> &'static mut [u8] {
+        <Self as Enable>::enable();
+
+        let pwr = unsafe { &*PWR::ptr() };
+        if retain {
+            pwr.csr.modify(|_, w| w.bre().set_bit());
+            while pwr.csr.read().brr().bit_is_clear() {}
+        }
+
+        unsafe { core::slice::from_raw_parts_mut(Self::ADDRESS as *mut u8, Self::SIZE) }
+    }
+
+    /// Stops the backup regulator, the backup SRAM is then lost in Standby and on VBAT
+    pub fn disable_retention() {
+        let pwr = unsafe { &*PWR::ptr() };
+        pwr.csr.modify(|_, w| w.bre().clear_bit());
+    }
+}
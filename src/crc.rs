@@ -0,0 +1,248 @@
+//! CRC calculation unit
+//!
+//! Computes the CRC-32 with the Ethernet polynomial 0x04C11DB7, from an initial value of
+//! 0xFFFFFFFF, without reflection or final XOR (CRC-32/MPEG-2). The unit takes 32-bit words, most
+//! significant bit first.
+//!
+//! Bytes are grouped into little-endian words, so feeding a `[u32]` gives the same result as
+//! feeding its bytes as they are laid out in memory. A tail of less than 4 bytes is padded with
+//! zeros, in the most significant bytes, when the result is read.
+//!
+//! ```no_run
+//! use core::hash::Hasher;
+//! use stm32f4xx_hal::{crc::CrcExt, stm32};
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let mut crc = dp.CRC.constrain();
+//!
+//! crc.feed_words(&[0x1234_5678, 0x9ABC_DEF0]);
+//! let checksum = crc.result();
+//!
+//! crc.reset();
+//! crc.write(b"hello");
+//! let hash = crc.finish();
+//! ```
+
+use core::hash::Hasher;
+
+use crate::dma::{
+    buffer::DmaWords,
+    config::DmaConfig,
+    traits::{Channel, DMASet, Direction, Stream},
+    MemoryToMemory, Transfer,
+};
+use crate::rcc::{Enable, Reset};
+use crate::stm32::CRC;
+
+/// Extension trait that constrains the `CRC` peripheral
+pub trait CrcExt {
+    /// Enables and resets the CRC unit
+    fn constrain(self) -> Crc;
+}
+
+impl CrcExt for CRC {
+    fn constrain(self) -> Crc {
+        CRC::enable();
+        CRC::reset();
+        Crc {
+            crc: self,
+            tail: [0; 4],
+            tail_len: 0,
+        }
+    }
+}
+
+/// CRC calculation unit
+pub struct Crc {
+    crc: CRC,
+    /// Bytes that don't fill a word yet
+    tail: [u8; 4],
+    tail_len: usize,
+}
+
+impl Crc {
+    /// Starts a new calculation
+    pub fn reset(&mut self) {
+        self.crc.cr.write(|w| w.reset().set_bit());
+        self.tail_len = 0;
+    }
+
+    /// Feeds words
+    pub fn feed_words(&mut self, words: &[u32]) {
+        if self.tail_len != 0 {
+            for word in words {
+                self.feed_bytes(&word.to_le_bytes());
+            }
+            return;
+        }
+        for &word in words {
+            self.write_dr(word);
+        }
+    }
+
+    /// Feeds bytes, see the [module documentation](self) for how they are grouped into words
+    pub fn feed_bytes(&mut self, mut data: &[u8]) {
+        if self.tail_len != 0 {
+            let len = data.len().min(4 - self.tail_len);
+            self.tail[self.tail_len..self.tail_len + len].copy_from_slice(&data[..len]);
+            self.tail_len += len;
+            data = &data[len..];
+            if self.tail_len < 4 {
+                return;
+            }
+            self.write_dr(u32::from_le_bytes(self.tail));
+            self.tail_len = 0;
+        }
+
+        let mut words = data.chunks_exact(4);
+        for word in &mut words {
+            self.write_dr(u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+        }
+        let rest = words.remainder();
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.tail_len = rest.len();
+    }
+
+    /// Returns the CRC of the data fed since the last reset
+    ///
+    /// A pending tail is padded with zeros without being fed, so more data can follow.
+    pub fn result(&self) -> u32 {
+        let crc = self.crc.dr.read().bits();
+        if self.tail_len == 0 {
+            crc
+        } else {
+            let mut word = [0; 4];
+            word[..self.tail_len].copy_from_slice(&self.tail[..self.tail_len]);
+            update(crc, u32::from_le_bytes(word))
+        }
+    }
+
+    /// Feeds `data` with a memory-to-memory DMA transfer, the CPU is free until it completes
+    ///
+    /// # Panics
+    ///
+    /// Panics if bytes that don't fill a word were fed before, or `data` is longer than 65535
+    /// words.
+    pub fn feed_dma<STREAM, CHANNEL>(
+        self,
+        stream: STREAM,
+        data: &'static [u32],
+    ) -> CrcTransfer<STREAM, CHANNEL>
+    where
+        STREAM: Stream,
+        CHANNEL: Channel,
+        (STREAM, CHANNEL, MemoryToMemory<u32>, MemoryToMemory<u32>): DMASet,
+    {
+        assert_eq!(self.tail_len, 0);
+        assert!(data.len() <= usize::from(u16::MAX));
+
+        // The data register is handed over as many times as there are words to feed
+        let dr = DmaWords {
+            ptr: &self.crc.dr as *const _ as *mut u32,
+            len: data.len(),
+        };
+        let source = DmaWords {
+            ptr: data.as_ptr() as *mut u32,
+            len: data.len(),
+        };
+        // The data register is the destination, it must not be incremented
+        let config = DmaConfig::default()
+            .memory_increment(false)
+            .peripheral_increment(true)
+            .fifo_enable(true);
+        let mut transfer = Transfer::init(stream, MemoryToMemory::new(), dr, Some(source), config);
+        transfer.start(|_| {});
+
+        CrcTransfer {
+            transfer,
+            crc: self,
+        }
+    }
+
+    /// Releases the CRC peripheral
+    pub fn release(self) -> CRC {
+        self.crc
+    }
+
+    fn write_dr(&mut self, word: u32) {
+        self.crc.dr.write(|w| unsafe { w.bits(word) });
+    }
+}
+
+impl Hasher for Crc {
+    fn write(&mut self, bytes: &[u8]) {
+        self.feed_bytes(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        u64::from(self.result())
+    }
+}
+
+/// A memory-to-memory DMA transfer that feeds the CRC unit
+pub struct CrcTransfer<STREAM, CHANNEL>
+where
+    STREAM: Stream,
+{
+    transfer: Transfer<STREAM, CHANNEL, MemoryToMemory<u32>, MemoryToMemory<u32>, DmaWords>,
+    crc: Crc,
+}
+
+impl<STREAM, CHANNEL> CrcTransfer<STREAM, CHANNEL>
+where
+    STREAM: Stream,
+    CHANNEL: Channel,
+    (STREAM, CHANNEL, MemoryToMemory<u32>, MemoryToMemory<u32>): DMASet,
+{
+    /// Returns true once all the data was fed
+    pub fn is_complete(&self) -> bool {
+        STREAM::get_transfer_complete_flag()
+    }
+
+    /// Waits for the transfer to complete, and returns the CRC unit, the stream and the data
+    pub fn wait(self) -> (Crc, STREAM, &'static [u32]) {
+        while !self.is_complete() {}
+
+        let (stream, _, _, source) = self.transfer.free();
+        // The source was created from a `&'static [u32]` in `Crc::feed_dma`
+        let source = source.unwrap();
+        let data = unsafe { core::slice::from_raw_parts(source.ptr, source.len) };
+
+        (self.crc, stream, data)
+    }
+}
+
+/// Software model of the CRC unit, feeds `word` to a unit that holds `crc`
+fn update(mut crc: u32, word: u32) -> u32 {
+    crc ^= word;
+    for _ in 0..32 {
+        crc = if crc & 0x8000_0000 != 0 {
+            (crc << 1) ^ 0x04C1_1DB7
+        } else {
+            crc << 1
+        };
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crc_of(words: &[u32]) -> u32 {
+        words
+            .iter()
+            .fold(0xFFFF_FFFF, |crc, &word| update(crc, word))
+    }
+
+    #[test]
+    fn reference_model() {
+        assert_eq!(crc_of(&[]), 0xFFFF_FFFF);
+        assert_eq!(crc_of(&[0]), 0xC704_DD7B);
+        // CRC-32/MPEG-2 check value, with the bytes in the order the unit takes them
+        assert_eq!(
+            crc_of(&[u32::from_be_bytes(*b"1234"), u32::from_be_bytes(*b"5678"),]),
+            0x49E3_C2FB
+        );
+    }
+}
@@ -0,0 +1,965 @@
+//! Cryptographic processor
+//!
+//! AES-128/192/256 in ECB, CBC and CTR modes and DES/TDES in ECB and CBC modes, plus AES-GCM and
+//! AES-CCM authenticated encryption on the STM32F437/439/479.
+//!
+//! The processor takes 32-bit words, whose bytes are swapped first as selected by the
+//! [`DataType`]. Bytes are grouped into little-endian words, as they are laid out in memory, so
+//! with the default `DataType::Byte` the data is processed in its usual byte order, whether it is
+//! handed over as bytes or as words by DMA.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{
+//!     cryp::{Algorithm, CrypExt, Direction},
+//!     stm32,
+//! };
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let mut cryp = dp.CRYP.constrain();
+//!
+//! let key = [0x2b; 16];
+//! let iv = [0; 16];
+//! let mut ciphertext = [0; 32];
+//! cryp.init(Algorithm::AesCbc, Direction::Encrypt, &key, &iv)
+//!     .unwrap();
+//! cryp.process(b"two blocks of plaintext, padded.", &mut ciphertext)
+//!     .unwrap();
+//! ```
+
+use crate::dma::{
+    buffer::WriteBuffer,
+    config::DmaConfig,
+    traits::{Channel, DMASet, PeriAddress, Stream},
+    MemoryToPeripheral, PeripheralToMemory, Transfer,
+};
+use crate::rcc::{Enable, Reset};
+use crate::stm32::CRYP;
+
+/// ALGOMODE for the AES key preparation
+const MODE_AES_KEY: u8 = 0b0111;
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+const MODE_AES_GCM: u8 = 0b1000;
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+const MODE_AES_CCM: u8 = 0b1001;
+
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+const PHASE_HEADER: u8 = 0b01;
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+const PHASE_PAYLOAD: u8 = 0b10;
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+const PHASE_FINAL: u8 = 0b11;
+
+/// Cipher and chaining mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    /// DES, electronic codebook
+    DesEcb,
+    /// DES, cipher block chaining
+    DesCbc,
+    /// Triple DES, electronic codebook
+    TdesEcb,
+    /// Triple DES, cipher block chaining
+    TdesCbc,
+    /// AES, electronic codebook
+    AesEcb,
+    /// AES, cipher block chaining
+    AesCbc,
+    /// AES, counter
+    AesCtr,
+}
+
+impl Algorithm {
+    /// Returns the block size in bytes
+    pub fn block_size(self) -> usize {
+        match self.cipher() {
+            Cipher::Aes => 16,
+            Cipher::Des | Cipher::Tdes => 8,
+        }
+    }
+
+    /// Returns the length of the initialization vector in bytes, 0 for the ECB modes
+    pub fn iv_len(self) -> usize {
+        match self {
+            Algorithm::DesEcb | Algorithm::TdesEcb | Algorithm::AesEcb => 0,
+            Algorithm::DesCbc | Algorithm::TdesCbc => 8,
+            Algorithm::AesCbc | Algorithm::AesCtr => 16,
+        }
+    }
+
+    fn cipher(self) -> Cipher {
+        match self {
+            Algorithm::DesEcb | Algorithm::DesCbc => Cipher::Des,
+            Algorithm::TdesEcb | Algorithm::TdesCbc => Cipher::Tdes,
+            Algorithm::AesEcb | Algorithm::AesCbc | Algorithm::AesCtr => Cipher::Aes,
+        }
+    }
+
+    /// ALGOMODE
+    fn mode(self) -> u8 {
+        match self {
+            Algorithm::TdesEcb => 0b000,
+            Algorithm::TdesCbc => 0b001,
+            Algorithm::DesEcb => 0b010,
+            Algorithm::DesCbc => 0b011,
+            Algorithm::AesEcb => 0b100,
+            Algorithm::AesCbc => 0b101,
+            Algorithm::AesCtr => 0b110,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Cipher {
+    Des,
+    Tdes,
+    Aes,
+}
+
+/// Processing direction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+/// Swapping applied to each 32-bit word of data, in and out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DataType {
+    /// No swapping
+    Word = 0b00,
+    /// The half-words are swapped
+    HalfWord = 0b01,
+    /// The bytes are swapped
+    Byte = 0b10,
+    /// All the bits are reversed
+    Bit = 0b11,
+}
+
+/// CRYP error
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// The key length doesn't match the algorithm
+    InvalidKey,
+    /// The initialization vector or nonce length doesn't match the algorithm
+    InvalidIv,
+    /// The data or tag length is not supported, or the output length doesn't match the input
+    InvalidLength,
+    /// The tag doesn't match the data
+    #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+    AuthenticationFailed,
+}
+
+/// Extension trait that constrains the `CRYP` peripheral
+pub trait CrypExt {
+    /// Enables and resets the cryptographic processor
+    fn constrain(self) -> Cryp;
+}
+
+impl CrypExt for CRYP {
+    fn constrain(self) -> Cryp {
+        CRYP::enable();
+        CRYP::reset();
+        Cryp {
+            cryp: self,
+            data_type: DataType::Byte,
+            algorithm: None,
+        }
+    }
+}
+
+/// Cryptographic processor
+pub struct Cryp {
+    cryp: CRYP,
+    data_type: DataType,
+    /// Set up by `init`
+    algorithm: Option<Algorithm>,
+}
+
+impl Cryp {
+    /// Selects the swapping applied to the data, from the next call to [`init`](Self::init)
+    pub fn set_data_type(&mut self, data_type: DataType) {
+        self.data_type = data_type;
+    }
+
+    /// Loads `key` and `iv` and gets ready to process data with `algorithm`
+    ///
+    /// DES takes a 8-byte key, TDES a 24-byte key, and AES a 16, 24 or 32-byte key. `iv` must be
+    /// [`Algorithm::iv_len`] long: empty for the ECB modes, and the initial counter block for CTR.
+    pub fn init(
+        &mut self,
+        algorithm: Algorithm,
+        direction: Direction,
+        key: &[u8],
+        iv: &[u8],
+    ) -> Result<(), Error> {
+        self.algorithm = None;
+        if iv.len() != algorithm.iv_len() {
+            return Err(Error::InvalidIv);
+        }
+        self.setup(
+            algorithm.cipher(),
+            algorithm.mode(),
+            direction,
+            self.data_type,
+            key,
+            iv,
+        )?;
+
+        if direction == Direction::Decrypt
+            && (algorithm == Algorithm::AesEcb || algorithm == Algorithm::AesCbc)
+        {
+            // The decryption key schedule is prepared from the key first
+            self.set_mode(MODE_AES_KEY);
+            self.enable();
+            self.disable();
+            self.set_mode(algorithm.mode());
+        }
+        self.enable();
+
+        self.algorithm = Some(algorithm);
+        Ok(())
+    }
+
+    /// Processes `input` into `output`, which must be as long
+    ///
+    /// The chaining state is kept between calls, so a message can be processed in several parts.
+    /// The length must be a multiple of the block size, except for the last part in CTR mode.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`init`](Self::init) wasn't called.
+    pub fn process(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        let algorithm = self.algorithm.expect("CRYP not initialized");
+        let block_size = algorithm.block_size();
+        if output.len() != input.len()
+            || (algorithm != Algorithm::AesCtr && input.len() % block_size != 0)
+        {
+            return Err(Error::InvalidLength);
+        }
+
+        for (input, output) in input.chunks(block_size).zip(output.chunks_mut(block_size)) {
+            self.process_block(input, output, block_size);
+        }
+        Ok(())
+    }
+
+    /// Processes `input` into `output` with DMA transfers, the CPU is free until they complete
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`init`](Self::init) wasn't called, or if the buffers don't have the same length,
+    /// a whole number of blocks of at most 65535 words.
+    pub fn process_dma<IN, OUT, CHANNEL, BUF>(
+        self,
+        in_stream: IN,
+        out_stream: OUT,
+        mut input: BUF,
+        mut output: BUF,
+    ) -> CrypTransfer<IN, OUT, CHANNEL, BUF>
+    where
+        IN: Stream,
+        OUT: Stream,
+        CHANNEL: Channel,
+        BUF: WriteBuffer<Word = u32> + 'static,
+        (IN, CHANNEL, DmaIn, MemoryToPeripheral): DMASet,
+        (OUT, CHANNEL, DmaOut, PeripheralToMemory): DMASet,
+    {
+        let algorithm = self.algorithm.expect("CRYP not initialized");
+        // NOTE(unsafe) Only the lengths are used, the buffers are handed to the transfers next
+        let (_, input_len) = unsafe { input.write_buffer() };
+        let (_, output_len) = unsafe { output.write_buffer() };
+        assert_eq!(input_len, output_len);
+        assert_eq!(input_len % (algorithm.block_size() / 4), 0);
+        assert!(input_len <= usize::from(u16::MAX));
+
+        let config = DmaConfig::default().memory_increment(true);
+        let mut output = Transfer::init(out_stream, DmaOut(()), output, None, config);
+        let mut input = Transfer::init(in_stream, DmaIn(()), input, None, config);
+        output.start(|_| {});
+        input.start(|_| {});
+        self.cryp
+            .dmacr
+            .write(|w| w.dien().set_bit().doen().set_bit());
+
+        CrypTransfer {
+            input,
+            output,
+            cryp: self,
+        }
+    }
+
+    /// Disables and releases the CRYP peripheral
+    pub fn release(mut self) -> CRYP {
+        self.disable();
+        self.cryp
+    }
+
+    /// Loads the key and initialization vector, and selects the mode
+    fn setup(
+        &mut self,
+        cipher: Cipher,
+        mode: u8,
+        direction: Direction,
+        data_type: DataType,
+        key: &[u8],
+        iv: &[u8],
+    ) -> Result<(), Error> {
+        let (key, keysize) = key_registers(cipher, key)?;
+        let iv = iv_registers(iv);
+
+        self.disable();
+        self.set_mode(mode);
+        self.cryp.cr.modify(|_, w| unsafe {
+            w.algodir()
+                .bit(direction == Direction::Decrypt)
+                .datatype()
+                .bits(data_type as u8)
+                .keysize()
+                .bits(keysize)
+                .gcm_ccmph()
+                .bits(0)
+        });
+        for (regs, words) in self.cryp.key.iter().zip(key.chunks_exact(2)) {
+            regs.klr.write(|w| unsafe { w.bits(words[0]) });
+            regs.krr.write(|w| unsafe { w.bits(words[1]) });
+        }
+        for (regs, words) in self.cryp.init.iter().zip(iv.chunks_exact(2)) {
+            regs.ivlr.write(|w| unsafe { w.bits(words[0]) });
+            regs.ivrr.write(|w| unsafe { w.bits(words[1]) });
+        }
+        self.cryp.cr.modify(|_, w| w.fflush().set_bit());
+        Ok(())
+    }
+
+    fn set_mode(&mut self, mode: u8) {
+        self.cryp.cr.modify(|_, w| unsafe {
+            w.algomode0()
+                .bits(mode & 0b111)
+                .algomode3()
+                .bit(mode & 0b1000 != 0)
+        });
+    }
+
+    fn enable(&mut self) {
+        self.cryp.cr.modify(|_, w| w.crypen().set_bit());
+    }
+
+    /// Waits for the current block to be processed and disables the processor, which keeps its
+    /// state
+    fn disable(&mut self) {
+        while self.cryp.sr.read().busy().bit_is_set() {}
+        self.cryp.cr.modify(|_, w| w.crypen().clear_bit());
+    }
+
+    /// Processes one block, `input` is padded with zeros and the output truncated to `output`
+    fn process_block(&mut self, input: &[u8], output: &mut [u8], block_size: usize) {
+        let mut block = [0; 16];
+        block[..input.len()].copy_from_slice(input);
+
+        for word in block[..block_size].chunks_exact(4) {
+            self.write_word(word);
+        }
+        for word in block[..block_size].chunks_exact_mut(4) {
+            while self.cryp.sr.read().ofne().bit_is_clear() {}
+            word.copy_from_slice(&self.cryp.dout.read().bits().to_le_bytes());
+        }
+        output.copy_from_slice(&block[..output.len()]);
+    }
+
+    fn write_word(&mut self, word: &[u8]) {
+        while self.cryp.sr.read().ifnf().bit_is_clear() {}
+        let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        self.cryp.din.write(|w| unsafe { w.bits(word) });
+    }
+}
+
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+impl Cryp {
+    /// Encrypts `plaintext` into `ciphertext` with AES-GCM, and returns the tag authenticating
+    /// `aad` and the ciphertext
+    ///
+    /// This replaces the setup made by [`init`](Self::init).
+    pub fn gcm_encrypt(
+        &mut self,
+        key: &[u8],
+        iv: &[u8; 12],
+        aad: &[u8],
+        plaintext: &[u8],
+        ciphertext: &mut [u8],
+    ) -> Result<[u8; 16], Error> {
+        if ciphertext.len() != plaintext.len() {
+            return Err(Error::InvalidLength);
+        }
+        if plaintext.len() % 16 == 0 {
+            return self.gcm(
+                Direction::Encrypt,
+                key,
+                iv,
+                aad,
+                plaintext,
+                Some(ciphertext),
+            );
+        }
+
+        // The processor hashes the whole last block it outputs, including what lies past the end
+        // of the message. The ciphertext is computed in CTR mode instead, and the tag by
+        // decrypting it, which hashes the input padded with zeros.
+        self.ctr(key, &gcm_counter(iv, 2), plaintext, ciphertext)?;
+        self.gcm(Direction::Decrypt, key, iv, aad, ciphertext, None)
+    }
+
+    /// Decrypts `ciphertext` into `plaintext` with AES-GCM, and checks `tag`, which may be
+    /// truncated to 4 bytes
+    ///
+    /// `plaintext` is zeroed if the tag doesn't match. This replaces the setup made by
+    /// [`init`](Self::init).
+    pub fn gcm_decrypt(
+        &mut self,
+        key: &[u8],
+        iv: &[u8; 12],
+        aad: &[u8],
+        ciphertext: &[u8],
+        plaintext: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        if plaintext.len() != ciphertext.len() || !(4..=16).contains(&tag.len()) {
+            return Err(Error::InvalidLength);
+        }
+        let expected = self.gcm(
+            Direction::Decrypt,
+            key,
+            iv,
+            aad,
+            ciphertext,
+            Some(plaintext),
+        )?;
+        check_tag(&expected, tag, plaintext)
+    }
+
+    /// Encrypts `plaintext` into `ciphertext` with AES-CCM, and writes the tag authenticating
+    /// `aad` and the plaintext to `tag`
+    ///
+    /// The nonce is 7 to 13 bytes long, and the tag 4 to 16 bytes, an even number. This replaces
+    /// the setup made by [`init`](Self::init).
+    pub fn ccm_encrypt(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+        ciphertext: &mut [u8],
+        tag: &mut [u8],
+    ) -> Result<(), Error> {
+        if ciphertext.len() != plaintext.len() {
+            return Err(Error::InvalidLength);
+        }
+        let computed = self.ccm(
+            Direction::Encrypt,
+            key,
+            nonce,
+            aad,
+            plaintext,
+            Some(ciphertext),
+            tag.len(),
+        )?;
+        tag.copy_from_slice(&computed[..tag.len()]);
+        Ok(())
+    }
+
+    /// Decrypts `ciphertext` into `plaintext` with AES-CCM, and checks `tag`
+    ///
+    /// `plaintext` is zeroed if the tag doesn't match. This replaces the setup made by
+    /// [`init`](Self::init).
+    pub fn ccm_decrypt(
+        &mut self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+        plaintext: &mut [u8],
+        tag: &[u8],
+    ) -> Result<(), Error> {
+        if plaintext.len() != ciphertext.len() {
+            return Err(Error::InvalidLength);
+        }
+        let expected = if ciphertext.len() % 16 == 0 {
+            self.ccm(
+                Direction::Decrypt,
+                key,
+                nonce,
+                aad,
+                ciphertext,
+                Some(plaintext),
+                tag.len(),
+            )?
+        } else {
+            // The processor authenticates the whole last block it outputs, including what lies
+            // past the end of the message. The plaintext is computed in CTR mode instead, and the
+            // tag by encrypting it, which authenticates the input padded with zeros.
+            let (_, mut counter) = ccm_blocks(nonce, tag.len(), aad.len(), ciphertext.len())?;
+            counter[15] = 1;
+            self.ctr(key, &counter, ciphertext, plaintext)?;
+            self.ccm(
+                Direction::Encrypt,
+                key,
+                nonce,
+                aad,
+                plaintext,
+                None,
+                tag.len(),
+            )?
+        };
+        check_tag(&expected, tag, plaintext)
+    }
+
+    /// Runs AES-GCM, and returns the tag
+    fn gcm(
+        &mut self,
+        direction: Direction,
+        key: &[u8],
+        iv: &[u8; 12],
+        aad: &[u8],
+        input: &[u8],
+        output: Option<&mut [u8]>,
+    ) -> Result<[u8; 16], Error> {
+        self.algorithm = None;
+        self.setup(
+            Cipher::Aes,
+            MODE_AES_GCM,
+            direction,
+            DataType::Byte,
+            key,
+            &gcm_counter(iv, 2),
+        )?;
+
+        // Computes the hash subkey, CRYPEN is cleared once done
+        self.enable();
+        while self.cryp.cr.read().crypen().bit_is_set() {}
+
+        self.header(&[aad]);
+        self.payload(input, output);
+
+        let mut tag = [0; 16];
+        self.set_phase(PHASE_FINAL);
+        self.process_block(&gcm_lengths(aad.len(), input.len()), &mut tag, 16);
+        self.disable();
+        Ok(tag)
+    }
+
+    /// Runs AES-CCM, and returns the tag
+    #[allow(clippy::too_many_arguments)]
+    fn ccm(
+        &mut self,
+        direction: Direction,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        input: &[u8],
+        output: Option<&mut [u8]>,
+        tag_len: usize,
+    ) -> Result<[u8; 16], Error> {
+        let (b0, counter) = ccm_blocks(nonce, tag_len, aad.len(), input.len())?;
+        let mut first_counter = counter;
+        first_counter[15] = 1;
+
+        self.algorithm = None;
+        self.setup(
+            Cipher::Aes,
+            MODE_AES_CCM,
+            direction,
+            DataType::Byte,
+            key,
+            &first_counter,
+        )?;
+
+        // Processes B0, CRYPEN is cleared once done
+        self.enable();
+        for word in b0.chunks_exact(4) {
+            self.write_word(word);
+        }
+        while self.cryp.cr.read().crypen().bit_is_set() {}
+
+        if !aad.is_empty() {
+            let (prefix, prefix_len) = ccm_aad_prefix(aad.len());
+            self.header(&[&prefix[..prefix_len], aad]);
+        }
+        self.payload(input, output);
+
+        let mut tag = [0; 16];
+        self.set_phase(PHASE_FINAL);
+        self.process_block(&counter, &mut tag, 16);
+        self.disable();
+        Ok(tag)
+    }
+
+    /// Runs AES-CTR from `counter`
+    fn ctr(
+        &mut self,
+        key: &[u8],
+        counter: &[u8; 16],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        self.algorithm = None;
+        self.setup(
+            Cipher::Aes,
+            Algorithm::AesCtr.mode(),
+            Direction::Encrypt,
+            DataType::Byte,
+            key,
+            counter,
+        )?;
+        self.enable();
+        for (input, output) in input.chunks(16).zip(output.chunks_mut(16)) {
+            self.process_block(input, output, 16);
+        }
+        self.disable();
+        Ok(())
+    }
+
+    /// Runs the header phase with the concatenation of `parts`, padded with zeros
+    fn header(&mut self, parts: &[&[u8]]) {
+        if parts.iter().all(|part| part.is_empty()) {
+            return;
+        }
+        self.set_phase(PHASE_HEADER);
+
+        let mut block = [0; 16];
+        let mut len = 0;
+        for &byte in parts.iter().flat_map(|part| part.iter()) {
+            block[len] = byte;
+            len += 1;
+            if len == 16 {
+                block.chunks_exact(4).for_each(|word| self.write_word(word));
+                len = 0;
+            }
+        }
+        if len != 0 {
+            block[len..].iter_mut().for_each(|byte| *byte = 0);
+            block.chunks_exact(4).for_each(|word| self.write_word(word));
+        }
+        while self.cryp.sr.read().ifem().bit_is_clear() {}
+    }
+
+    /// Runs the payload phase, the output is dropped if `output` is `None`
+    fn payload(&mut self, input: &[u8], mut output: Option<&mut [u8]>) {
+        if input.is_empty() {
+            return;
+        }
+        self.set_phase(PHASE_PAYLOAD);
+
+        for (i, input) in input.chunks(16).enumerate() {
+            let mut block = [0; 16];
+            self.process_block(input, &mut block[..input.len()], 16);
+            if let Some(output) = output.as_deref_mut() {
+                output[i * 16..][..input.len()].copy_from_slice(&block[..input.len()]);
+            }
+        }
+    }
+
+    fn set_phase(&mut self, phase: u8) {
+        self.disable();
+        self.cryp
+            .cr
+            .modify(|_, w| unsafe { w.gcm_ccmph().bits(phase) });
+        self.enable();
+    }
+}
+
+/// DMA transfers through the cryptographic processor
+pub struct CrypTransfer<IN, OUT, CHANNEL, BUF>
+where
+    IN: Stream,
+    OUT: Stream,
+    BUF: WriteBuffer<Word = u32> + 'static,
+{
+    input: Transfer<IN, CHANNEL, DmaIn, MemoryToPeripheral, BUF>,
+    output: Transfer<OUT, CHANNEL, DmaOut, PeripheralToMemory, BUF>,
+    cryp: Cryp,
+}
+
+impl<IN, OUT, CHANNEL, BUF> CrypTransfer<IN, OUT, CHANNEL, BUF>
+where
+    IN: Stream,
+    OUT: Stream,
+    CHANNEL: Channel,
+    BUF: WriteBuffer<Word = u32> + 'static,
+    (IN, CHANNEL, DmaIn, MemoryToPeripheral): DMASet,
+    (OUT, CHANNEL, DmaOut, PeripheralToMemory): DMASet,
+{
+    /// Returns true once all the output was read
+    pub fn is_complete(&self) -> bool {
+        OUT::get_transfer_complete_flag()
+    }
+
+    /// Waits for the transfers to complete, and returns the processor, the streams and the input
+    /// and output buffers
+    pub fn wait(self) -> (Cryp, IN, OUT, BUF, BUF) {
+        while !self.is_complete() {}
+
+        self.cryp.cryp.dmacr.reset();
+        let (in_stream, _, input, _) = self.input.free();
+        let (out_stream, _, output, _) = self.output.free();
+
+        (self.cryp, in_stream, out_stream, input, output)
+    }
+}
+
+/// Input FIFO of the processor, the destination of the DMA IN stream
+pub struct DmaIn(());
+
+unsafe impl PeriAddress for DmaIn {
+    type MemSize = u32;
+
+    fn address(&self) -> u32 {
+        unsafe { &(*CRYP::ptr()).din as *const _ as u32 }
+    }
+}
+
+/// Output FIFO of the processor, the source of the DMA OUT stream
+pub struct DmaOut(());
+
+unsafe impl PeriAddress for DmaOut {
+    type MemSize = u32;
+
+    fn address(&self) -> u32 {
+        unsafe { &(*CRYP::ptr()).dout as *const _ as u32 }
+    }
+}
+
+/// Returns the values of the key registers, K0LR to K3RR, and KEYSIZE for `key`
+fn key_registers(cipher: Cipher, key: &[u8]) -> Result<([u32; 8], u8), Error> {
+    // The key ends in K3RR, except for DES which only uses K1
+    let (first, keysize) = match (cipher, key.len()) {
+        (Cipher::Des, 8) | (Cipher::Tdes, 24) => (2, 0),
+        (Cipher::Aes, 16) => (4, 0b00),
+        (Cipher::Aes, 24) => (2, 0b01),
+        (Cipher::Aes, 32) => (0, 0b10),
+        _ => return Err(Error::InvalidKey),
+    };
+
+    let mut words = [0; 8];
+    for (word, bytes) in words[first..].iter_mut().zip(key.chunks_exact(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    Ok((words, keysize))
+}
+
+/// Returns the values of the initialization vector registers, IV0LR to IV1RR, for `iv`
+fn iv_registers(iv: &[u8]) -> [u32; 4] {
+    let mut words = [0; 4];
+    for (word, bytes) in words.iter_mut().zip(iv.chunks_exact(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+/// Returns the GCM counter block for `iv` and `counter`
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+fn gcm_counter(iv: &[u8; 12], counter: u32) -> [u8; 16] {
+    let mut block = [0; 16];
+    block[..12].copy_from_slice(iv);
+    block[12..].copy_from_slice(&counter.to_be_bytes());
+    block
+}
+
+/// Returns the last GCM block, with the lengths in bits of the additional data and the payload
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+fn gcm_lengths(aad_len: usize, len: usize) -> [u8; 16] {
+    let mut block = [0; 16];
+    block[..8].copy_from_slice(&(aad_len as u64 * 8).to_be_bytes());
+    block[8..].copy_from_slice(&(len as u64 * 8).to_be_bytes());
+    block
+}
+
+/// Returns the first CCM block, B0, and the counter block of the tag, Ctr0
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+fn ccm_blocks(
+    nonce: &[u8],
+    tag_len: usize,
+    aad_len: usize,
+    len: usize,
+) -> Result<([u8; 16], [u8; 16]), Error> {
+    if !(7..=13).contains(&nonce.len()) {
+        return Err(Error::InvalidIv);
+    }
+    // The length is stored in the bytes left by the nonce
+    let q = 15 - nonce.len();
+    let len = len as u64;
+    if !(4..=16).contains(&tag_len) || tag_len % 2 != 0 || (q < 8 && len >> (8 * q) != 0) {
+        return Err(Error::InvalidLength);
+    }
+
+    let mut counter = [0; 16];
+    counter[0] = (q - 1) as u8;
+    counter[1..=nonce.len()].copy_from_slice(nonce);
+
+    let mut b0 = counter;
+    b0[0] |= (u8::from(aad_len != 0) << 6) | (((tag_len - 2) / 2) as u8) << 3;
+    b0[16 - q..].copy_from_slice(&len.to_be_bytes()[8 - q..]);
+
+    Ok((b0, counter))
+}
+
+/// Returns the encoded length of the CCM additional data, which precedes it, and its length
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+fn ccm_aad_prefix(aad_len: usize) -> ([u8; 6], usize) {
+    let mut prefix = [0; 6];
+    if aad_len < 0xFF00 {
+        prefix[..2].copy_from_slice(&(aad_len as u16).to_be_bytes());
+        (prefix, 2)
+    } else {
+        prefix[..2].copy_from_slice(&[0xFF, 0xFE]);
+        prefix[2..].copy_from_slice(&(aad_len as u32).to_be_bytes());
+        (prefix, 6)
+    }
+}
+
+/// Compares the tags in constant time, and zeroes `plaintext` if they don't match
+#[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+fn check_tag(expected: &[u8; 16], tag: &[u8], plaintext: &mut [u8]) -> Result<(), Error> {
+    let diff = expected
+        .iter()
+        .zip(tag)
+        .fold(0, |diff, (a, b)| diff | (a ^ b));
+    if diff == 0 {
+        Ok(())
+    } else {
+        plaintext.iter_mut().for_each(|byte| *byte = 0);
+        Err(Error::AuthenticationFailed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counting(first: u8) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = first + i as u8;
+        }
+        bytes
+    }
+
+    fn hex(s: &str) -> [u8; 16] {
+        let mut block = [0; 16];
+        for (byte, i) in block.iter_mut().zip((0..s.len()).step_by(2)) {
+            *byte = u8::from_str_radix(&s[i..i + 2], 16).unwrap();
+        }
+        block
+    }
+
+    #[test]
+    fn key_loading() {
+        // FIPS-197 appendix C keys
+        let key = counting(0);
+        assert_eq!(
+            key_registers(Cipher::Aes, &key[..16]),
+            Ok((
+                [
+                    0,
+                    0,
+                    0,
+                    0,
+                    0x0001_0203,
+                    0x0405_0607,
+                    0x0809_0A0B,
+                    0x0C0D_0E0F
+                ],
+                0b00
+            ))
+        );
+        assert_eq!(key_registers(Cipher::Aes, &key[..24]).unwrap().1, 0b01);
+        assert_eq!(
+            key_registers(Cipher::Aes, &key),
+            Ok((
+                [
+                    0x0001_0203,
+                    0x0405_0607,
+                    0x0809_0A0B,
+                    0x0C0D_0E0F,
+                    0x1011_1213,
+                    0x1415_1617,
+                    0x1819_1A1B,
+                    0x1C1D_1E1F
+                ],
+                0b10
+            ))
+        );
+
+        // DES only uses K1, TDES K1 to K3
+        assert_eq!(
+            key_registers(Cipher::Des, &key[..8]).unwrap().0,
+            [0, 0, 0x0001_0203, 0x0405_0607, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            key_registers(Cipher::Tdes, &key[..24]).unwrap().0[2..],
+            [
+                0x0001_0203,
+                0x0405_0607,
+                0x0809_0A0B,
+                0x0C0D_0E0F,
+                0x1011_1213,
+                0x1415_1617
+            ]
+        );
+
+        assert_eq!(
+            key_registers(Cipher::Aes, &key[..8]),
+            Err(Error::InvalidKey)
+        );
+        assert_eq!(
+            key_registers(Cipher::Tdes, &key[..16]),
+            Err(Error::InvalidKey)
+        );
+    }
+
+    #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+    #[test]
+    fn gcm_blocks() {
+        // Test case 4 of the GCM specification, 20 bytes of additional data and 60 of plaintext
+        let iv = [
+            0xCA, 0xFE, 0xBA, 0xBE, 0xFA, 0xCE, 0xDB, 0xAD, 0xDE, 0xCA, 0xF8, 0x88,
+        ];
+        assert_eq!(
+            iv_registers(&gcm_counter(&iv, 2)),
+            [0xCAFE_BABE, 0xFACE_DBAD, 0xDECA_F888, 0x0000_0002]
+        );
+        assert_eq!(gcm_lengths(20, 60), hex("00000000000000a000000000000001e0"));
+    }
+
+    #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+    #[test]
+    fn ccm_formatting() {
+        // Examples 1 to 3 of NIST SP 800-38C appendix C
+        let nonce = &counting(0x10)[..13];
+        assert_eq!(
+            ccm_blocks(&nonce[..7], 4, 8, 4),
+            Ok((
+                hex("4f101112131415160000000000000004"),
+                hex("07101112131415160000000000000000")
+            ))
+        );
+        assert_eq!(
+            ccm_blocks(&nonce[..8], 6, 16, 16).unwrap().0,
+            hex("56101112131415161700000000000010")
+        );
+        assert_eq!(
+            ccm_blocks(&nonce[..12], 8, 20, 24),
+            Ok((
+                hex("5a101112131415161718191a1b000018"),
+                hex("02101112131415161718191a1b000000")
+            ))
+        );
+        assert_eq!(ccm_blocks(&nonce[..12], 4, 0, 0).unwrap().0[0], 0x0A);
+
+        assert_eq!(ccm_aad_prefix(8), ([0, 8, 0, 0, 0, 0], 2));
+        assert_eq!(ccm_aad_prefix(0x1_0000), ([0xFF, 0xFE, 0, 1, 0, 0], 6));
+
+        assert_eq!(ccm_blocks(&nonce[..6], 4, 0, 0), Err(Error::InvalidIv));
+        assert_eq!(ccm_blocks(&nonce[..7], 5, 0, 0), Err(Error::InvalidLength));
+        assert_eq!(ccm_blocks(nonce, 4, 0, 0x1_0000), Err(Error::InvalidLength));
+        assert!(ccm_blocks(nonce, 16, 0, 0xFFFF).is_ok());
+    }
+}
@@ -8,7 +8,7 @@ use core::mem;
 use crate::gpio::gpioa::{PA4, PA5};
 use crate::gpio::Analog;
 use crate::pac::DAC;
-use crate::stm32::RCC;
+use crate::rcc::{Enable, Reset};
 
 pub struct C1;
 pub struct C2;
@@ -42,15 +42,11 @@ pub fn dac<PINS>(_dac: DAC, _pins: PINS) -> PINS::Output
 where
     PINS: Pins<DAC>,
 {
-    // NOTE(unsafe) This executes only during initialisation
-    let rcc = unsafe { &(*RCC::ptr()) };
-
     // Enable DAC clocks
-    rcc.apb1enr.modify(|_, w| w.dacen().set_bit());
+    DAC::enable();
 
     // Reset DAC
-    rcc.apb1rstr.modify(|_, w| w.dacrst().set_bit());
-    rcc.apb1rstr.modify(|_, w| w.dacrst().clear_bit());
+    DAC::reset();
 
     unsafe { mem::MaybeUninit::uninit().assume_init() }
 }
@@ -0,0 +1,234 @@
+//! Debug support: peripherals frozen while the core is halted, and debugging in low-power modes
+//!
+//! When the core is halted by a debugger, the timers, watchdogs and RTC keep counting, the I2C
+//! SMBus timeouts fire and CAN goes bus-off. Every PAC peripheral that has a freeze bit on the
+//! device implements [`StopOnDebug`].
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{dbgmcu::DbgmcuExt, stm32};
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! dp.DBGMCU.stop_on_debug::<stm32::TIM2>(true);
+//! dp.DBGMCU.stop_on_debug::<stm32::IWDG>(true);
+//! dp.DBGMCU.debug_in_stop(true);
+//! ```
+
+use crate::stm32::DBGMCU;
+
+mod sealed {
+    pub trait Sealed {}
+}
+use sealed::Sealed;
+
+/// A peripheral that can be frozen while the core is halted
+///
+/// For the I2C peripherals only the SMBus timeout is frozen.
+pub trait StopOnDebug: Sealed {
+    /// Freezes the peripheral while the core is halted if `stop` is true
+    fn stop_on_debug(dbgmcu: &DBGMCU, stop: bool);
+}
+
+/// Extension trait for the `DBGMCU` peripheral
+pub trait DbgmcuExt {
+    /// Freezes peripheral `P` while the core is halted if `stop` is true
+    fn stop_on_debug<P: StopOnDebug>(&self, stop: bool);
+
+    /// Keeps the debugger connected in Sleep mode, by keeping HCLK running
+    fn debug_in_sleep(&self, enable: bool);
+
+    /// Keeps the debugger connected in Stop mode, by keeping HCLK and FCLK running from the HSI
+    fn debug_in_stop(&self, enable: bool);
+
+    /// Keeps the debugger connected in Standby mode, by keeping the 1.2 V domain powered and
+    /// HCLK and FCLK running from the HSI
+    fn debug_in_standby(&self, enable: bool);
+}
+
+impl DbgmcuExt for DBGMCU {
+    fn stop_on_debug<P: StopOnDebug>(&self, stop: bool) {
+        P::stop_on_debug(self, stop);
+    }
+
+    fn debug_in_sleep(&self, enable: bool) {
+        self.cr.modify(|_, w| w.dbg_sleep().bit(enable));
+    }
+
+    fn debug_in_stop(&self, enable: bool) {
+        self.cr.modify(|_, w| w.dbg_stop().bit(enable));
+    }
+
+    fn debug_in_standby(&self, enable: bool) {
+        self.cr.modify(|_, w| w.dbg_standby().bit(enable));
+    }
+}
+
+macro_rules! stop_on_debug {
+    ($apbx_fz:ident: { $($PER:ident: $dbg_stop:ident,)+ }) => {
+        $(
+            impl Sealed for crate::stm32::$PER {}
+
+            impl StopOnDebug for crate::stm32::$PER {
+                #[inline(always)]
+                fn stop_on_debug(dbgmcu: &DBGMCU, stop: bool) {
+                    dbgmcu.$apbx_fz.modify(|_, w| w.$dbg_stop().bit(stop));
+                }
+            }
+        )+
+    };
+}
+
+stop_on_debug! {
+    apb1_fz: {
+        IWDG: dbg_iwdg_stop,
+        TIM5: dbg_tim5_stop,
+        WWDG: dbg_wwdg_stop,
+    }
+}
+
+stop_on_debug! {
+    apb2_fz: {
+        TIM1: dbg_tim1_stop,
+        TIM9: dbg_tim9_stop,
+        TIM11: dbg_tim11_stop,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+stop_on_debug! {
+    apb1_fz: {
+        TIM2: dbg_tim2_stop,
+        TIM3: dbg_tim3_stop,
+        TIM4: dbg_tim4_stop,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+stop_on_debug! {
+    apb2_fz: {
+        TIM10: dbg_tim10_stop,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f410",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f446"
+))]
+stop_on_debug! {
+    apb1_fz: {
+        RTC: dbg_rtc_stop,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+stop_on_debug! {
+    apb1_fz: {
+        CAN1: dbg_can1_stop,
+        CAN2: dbg_can2_stop,
+        I2C1: dbg_j2c1_smbus_timeout,
+        I2C2: dbg_j2c2_smbus_timeout,
+        I2C3: dbg_j2c3smbus_timeout,
+        TIM6: dbg_tim6_stop,
+        TIM7: dbg_tim7_stop,
+        TIM12: dbg_tim12_stop,
+        TIM13: dbg_tim13_stop,
+        TIM14: dbg_tim14_stop,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+stop_on_debug! {
+    apb2_fz: {
+        TIM8: dbg_tim8_stop,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f410",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f423"
+))]
+stop_on_debug! {
+    apb1_fz: {
+        I2C1: dbg_i2c1_smbus_timeout,
+        I2C2: dbg_i2c2_smbus_timeout,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f423"
+))]
+stop_on_debug! {
+    apb1_fz: {
+        I2C3: dbg_i2c3smbus_timeout,
+    }
+}
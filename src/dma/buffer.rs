@@ -248,3 +248,19 @@ impl<T: WriteTarget> SealedWrite for MaybeUninit<T> {}
 unsafe impl<T: WriteTarget> WriteTarget for MaybeUninit<T> {
     type Word = T::Word;
 }
+
+/// Raw words handed to a DMA stream, by drivers that keep track of the memory themselves
+pub(crate) struct DmaWords {
+    pub(crate) ptr: *mut u32,
+    pub(crate) len: usize,
+}
+
+impl SealedWrite for DmaWords {}
+
+unsafe impl WriteBuffer for DmaWords {
+    type Word = u32;
+
+    unsafe fn write_buffer(&mut self) -> (*mut u32, usize) {
+        (self.ptr, self.len)
+    }
+}
@@ -15,12 +15,14 @@ use core::{
     sync::atomic::{compiler_fence, Ordering},
 };
 
+use crate::rcc::{Enable, Reset};
+
 pub mod buffer;
 pub mod traits;
 use buffer::WriteBuffer;
 use traits::{
     sealed::{Bits, Sealed},
-    Channel, DMASet, Direction, Instance, PeriAddress, RccEnable, Stream,
+    Channel, DMASet, Direction, Instance, PeriAddress, Stream,
 };
 
 /// Errors.
@@ -238,10 +240,11 @@ pub struct StreamsTuple<T>(
     pub Stream7<T>,
 );
 
-impl<T: RccEnable> StreamsTuple<T> {
+impl<T: Instance + Enable + Reset> StreamsTuple<T> {
     /// Splits the DMA peripheral into streams.
-    pub fn new(regs: T) -> Self {
-        regs.rcc_enable();
+    pub fn new(_regs: T) -> Self {
+        T::enable();
+        T::reset();
         Self(
             Stream0 { _dma: PhantomData },
             Stream1 { _dma: PhantomData },
@@ -1,8 +1,5 @@
 use super::*;
-use crate::{
-    bb,
-    pac::{self, DMA1, DMA2, RCC},
-};
+use crate::pac::{self, DMA1, DMA2};
 use core::ops::Deref;
 
 pub(crate) mod sealed {
@@ -236,38 +233,6 @@ impl Instance for DMA2 {
     }
 }
 
-/// Trait for peripheral's clock enabling.
-pub trait RccEnable: Sealed {
-    /// Enable the peripheral's clock in the RCC.
-    fn rcc_enable(&self);
-}
-
-impl RccEnable for pac::DMA1 {
-    fn rcc_enable(&self) {
-        unsafe {
-            //NOTE(unsafe) this reference will only be used for atomic writes with no side effects
-            let rcc = &(*RCC::ptr());
-            // Enable and reset the timer peripheral
-            bb::set(&rcc.ahb1enr, 21);
-            bb::set(&rcc.ahb1rstr, 21);
-            bb::clear(&rcc.ahb1rstr, 21);
-        }
-    }
-}
-
-impl RccEnable for pac::DMA2 {
-    fn rcc_enable(&self) {
-        unsafe {
-            //NOTE(unsafe) this reference will only be used for atomic writes with no side effects
-            let rcc = &(*RCC::ptr());
-            // Enable and reset the timer peripheral
-            bb::set(&rcc.ahb1enr, 22);
-            bb::set(&rcc.ahb1rstr, 22);
-            bb::clear(&rcc.ahb1rstr, 22);
-        }
-    }
-}
-
 macro_rules! tim_channels {
     ($($name:ident),+ $(,)*) => {
         $(
@@ -963,7 +928,6 @@ address!(
 ))]
 dma_map!(
     (Stream2<DMA1>, Channel3, pac::I2C3, PeripheralToMemory), //I2C3_RX
-    (Stream5<DMA2>, Channel2, pac::CRYP, PeripheralToMemory), //CRYP_OUT
     (Stream6<DMA2>, Channel2, pac::CRYP, MemoryToPeripheral), //CRYP_IN
     (Stream7<DMA2>, Channel2, pac::HASH, MemoryToPeripheral), //HASH_IN
 );
@@ -982,6 +946,28 @@ dma_map!(
 ))]
 address!((pac::HASH, din, u32), (pac::CRYP, din, u32),);
 
+#[cfg(any(
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f479",
+))]
+dma_map!(
+    (
+        Stream5<DMA2>,
+        Channel2,
+        crate::cryp::DmaOut,
+        PeripheralToMemory
+    ), //CRYP_OUT
+    (
+        Stream6<DMA2>,
+        Channel2,
+        crate::cryp::DmaIn,
+        MemoryToPeripheral
+    ), //CRYP_IN
+);
+
 /* Not sure how DAC works with DMA
 #[cfg(any(
     feature = "stm32f417",
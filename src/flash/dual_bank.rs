@@ -0,0 +1,177 @@
+//! Dual-bank firmware updates
+//!
+//! With the flash split into two banks, the firmware can be updated while it keeps running: the
+//! new image is written to the bank that is not running, checked, and the device is restarted
+//! from it by toggling the BFB2 option bit. The previous image stays in the other bank until the
+//! next update.
+//!
+//! The running bank is always mapped at the start of the flash memory and the other one right
+//! after it, so every image is linked for `FLASH_START`, whichever bank it ends up in.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{
+//!     crc::CrcExt,
+//!     flash::{DualBank, Flash},
+//!     rcc::VddRange,
+//!     stm32,
+//! };
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let mut dual_bank = DualBank::new(
+//!     Flash::new(dp.FLASH, VddRange::V2_7ToV3_6),
+//!     dp.CRC.constrain(),
+//! );
+//!
+//! // Received over the air, with its checksum
+//! let image: &[u8] = &[];
+//! let checksum = 0;
+//!
+//! dual_bank.erase_inactive().unwrap();
+//! dual_bank.program_inactive(0, image).unwrap();
+//! if dual_bank.checksum_inactive(image.len()) == checksum {
+//!     dual_bank.swap_and_reset().unwrap();
+//! }
+//! ```
+
+use core::convert::Infallible;
+
+use cortex_m::peripheral::SCB;
+
+use super::{flush_caches, Error, Flash};
+use crate::crc::Crc;
+use crate::rcc::Enable;
+use crate::stm32::SYSCFG;
+
+/// Physical flash bank
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bank {
+    Bank1,
+    Bank2,
+}
+
+impl Bank {
+    fn other(self) -> Self {
+        match self {
+            Bank::Bank1 => Bank::Bank2,
+            Bank::Bank2 => Bank::Bank1,
+        }
+    }
+}
+
+/// Updates the firmware in the bank that is not running
+pub struct DualBank {
+    flash: Flash,
+    crc: Crc,
+    active: Bank,
+}
+
+impl DualBank {
+    /// Takes the flash and the CRC unit, which is used to check the written image
+    ///
+    /// # Panics
+    ///
+    /// Panics if the flash is not split into two banks, see
+    /// [`SectorLayout::is_dual_bank`](super::SectorLayout::is_dual_bank).
+    pub fn new(flash: Flash, crc: Crc) -> Self {
+        assert!(flash.layout().is_dual_bank());
+
+        // FB_MODE is set by the bootloader when it starts the firmware from bank 2, which is
+        // then mapped at the start of the flash memory
+        SYSCFG::enable();
+        let memrm = unsafe { &(*SYSCFG::ptr()).memrm }.read().bits();
+        let active = if memrm & (1 << 8) != 0 {
+            Bank::Bank2
+        } else {
+            Bank::Bank1
+        };
+
+        DualBank { flash, crc, active }
+    }
+
+    /// Returns the bank the firmware is running from
+    pub fn active_bank(&self) -> Bank {
+        self.active
+    }
+
+    /// Returns the size of a bank in bytes, the largest image that fits
+    pub fn bank_size(&self) -> usize {
+        self.flash.layout().size() / 2
+    }
+
+    /// Erases the whole inactive bank
+    ///
+    /// This takes several seconds, the code in the active bank keeps running meanwhile.
+    pub fn erase_inactive(&mut self) -> Result<(), Error> {
+        let bank = self.active.other();
+        let psize = self.flash.psize as u8;
+
+        let mut unlocked = self.flash.unlocked();
+        unlocked.start();
+        let regs = &unlocked.flash.flash;
+        regs.cr.modify(|_, w| unsafe {
+            match bank {
+                Bank::Bank1 => w.mer().set_bit(),
+                Bank::Bank2 => w.mer1().set_bit(),
+            };
+            w.psize().bits(psize)
+        });
+        regs.cr.modify(|_, w| w.strt().set_bit());
+        let result = unlocked.wait();
+        let regs = &unlocked.flash.flash;
+        regs.cr
+            .modify(|_, w| w.mer().clear_bit().mer1().clear_bit());
+        flush_caches(regs);
+
+        result
+    }
+
+    /// Programs `data` at `offset` from the start of the inactive bank, which must have been
+    /// erased
+    pub fn program_inactive(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        let bank_size = self.bank_size();
+        if offset
+            .checked_add(data.len())
+            .map_or(true, |end| end > bank_size)
+        {
+            return Err(Error::OutOfBounds);
+        }
+        self.flash.unlocked().program(bank_size + offset, data)
+    }
+
+    /// Returns the contents of the inactive bank
+    pub fn read_inactive(&self) -> &[u8] {
+        &self.flash.read()[self.bank_size()..]
+    }
+
+    /// Computes the checksum of the first `len` bytes of the inactive bank with the CRC unit
+    ///
+    /// The bytes are fed as by [`Crc::feed_bytes`], the last word is padded with zeros.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than the bank.
+    pub fn checksum_inactive(&mut self, len: usize) -> u32 {
+        let bank_size = self.bank_size();
+        let data = &self.flash.read()[bank_size..bank_size + len];
+
+        self.crc.reset();
+        self.crc.feed_bytes(data);
+        self.crc.result()
+    }
+
+    /// Selects the inactive bank for the next boot with the BFB2 option bit and resets the device
+    ///
+    /// Only returns if the option bytes could not be programmed.
+    pub fn swap_and_reset(&mut self) -> Result<Infallible, Error> {
+        let mut option_bytes = self.flash.option_bytes();
+        option_bytes.boot_from_bank2 = self.active == Bank::Bank1;
+        self.flash.commit_option_bytes(&option_bytes)?;
+
+        SCB::sys_reset()
+    }
+
+    /// Releases the flash and the CRC unit
+    pub fn release(self) -> (Flash, Crc) {
+        (self.flash, self.crc)
+    }
+}
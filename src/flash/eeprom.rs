@@ -0,0 +1,614 @@
+//! EEPROM emulation on two flash sectors
+//!
+//! Variables are 32-bit values addressed by a 16-bit id. Every write appends a record to the
+//! active page, so a sector is only erased once it is full. The latest value of each variable is
+//! then copied to the other page, which becomes the active one.
+//!
+//! Each page starts with a header word that records its state, and each record is committed by a
+//! separate marker written after its data. After a power loss in the middle of a write or a page
+//! transfer, [`Eeprom::new`] completes or rolls back the interrupted operation, and every
+//! variable reads either its previous or its new value.
+//!
+//! A page transfer keeps a bitmap of the variable ids on the stack, which takes 8 KB.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{
+//!     flash::{
+//!         eeprom::{Eeprom, FlashPages},
+//!         Flash,
+//!     },
+//!     rcc::VddRange,
+//!     stm32,
+//! };
+//!
+//! const BOOT_COUNT: u16 = 1;
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let flash = Flash::new(dp.FLASH, VddRange::V2_7ToV3_6);
+//!
+//! // Sectors 2 and 3, 16 KB each
+//! let mut eeprom = Eeprom::new(FlashPages::new(flash, 0x8000, 0xC000)).unwrap();
+//! let boot_count = eeprom.read(BOOT_COUNT).unwrap_or(0);
+//! eeprom.write(BOOT_COUNT, boot_count + 1).unwrap();
+//! ```
+
+use super::{Flash, FlashSector};
+
+/// Erase and program operations on the two pages used by the EEPROM emulation
+///
+/// Programming can only clear bits, as on flash memory.
+pub trait Pages {
+    type Error;
+
+    /// Returns the size of a page in bytes
+    fn page_size(&self) -> usize;
+
+    /// Returns the contents of page 0 or 1
+    fn read(&self, page: usize) -> &[u8];
+
+    /// Sets all the bytes of a page to 0xFF
+    fn erase(&mut self, page: usize) -> Result<(), Self::Error>;
+
+    /// Programs `data` at `offset` in a page
+    fn program(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Two sectors of the internal flash
+pub struct FlashPages {
+    flash: Flash,
+    sectors: [FlashSector; 2],
+}
+
+impl FlashPages {
+    /// Uses the sectors that contain the bytes at `first` and `second`, from the start of the
+    /// flash memory. If their sizes differ, only the start of the larger one is used.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an offset is out of the flash memory or both are in the same sector.
+    pub fn new(flash: Flash, first: usize, second: usize) -> Self {
+        let layout = flash.layout();
+        let sectors = [
+            layout.sector(first).unwrap(),
+            layout.sector(second).unwrap(),
+        ];
+        assert_ne!(sectors[0], sectors[1]);
+        FlashPages { flash, sectors }
+    }
+
+    /// Releases the flash
+    pub fn release(self) -> Flash {
+        self.flash
+    }
+}
+
+impl Pages for FlashPages {
+    type Error = super::Error;
+
+    fn page_size(&self) -> usize {
+        self.sectors[0].size.min(self.sectors[1].size)
+    }
+
+    fn read(&self, page: usize) -> &[u8] {
+        let offset = self.sectors[page].offset;
+        &self.flash.read()[offset..offset + self.page_size()]
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), Self::Error> {
+        let offset = self.sectors[page].offset;
+        self.flash.unlocked().erase(offset).map(|_| ())
+    }
+
+    fn program(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), Self::Error> {
+        let offset = self.sectors[page].offset + offset;
+        self.flash.unlocked().program(offset, data)
+    }
+}
+
+/// EEPROM emulation error
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error<E> {
+    /// The flash operation failed
+    Flash(E),
+    /// There are more variables than fit in a page
+    Full,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(error: E) -> Self {
+        Error::Flash(error)
+    }
+}
+
+const HEADER_SIZE: usize = 8;
+const RECORD_SIZE: usize = 8;
+
+// Page header values, each one is programmed over the previous one
+const ERASED: u32 = 0xFFFF_FFFF;
+const RECEIVING: u32 = 0xEEEE_EEEE;
+const VALID: u32 = 0x0000_0000;
+
+/// Page state, from its header
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Erased,
+    /// The page is being filled by a transfer, or its header was only partly programmed
+    Receiving,
+    Valid,
+    Corrupt,
+}
+
+impl State {
+    fn of(page: &[u8]) -> Self {
+        match word(page, 0) {
+            ERASED => State::Erased,
+            VALID => State::Valid,
+            _ if page[..4].iter().all(|b| [0xFF, 0xEE, 0x00].contains(b)) => State::Receiving,
+            _ => State::Corrupt,
+        }
+    }
+}
+
+/// Contents of a record slot
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Slot {
+    Free,
+    /// Data written without its commit marker
+    Invalid,
+    Record {
+        id: u16,
+        value: u32,
+    },
+}
+
+impl Slot {
+    /// Record layout: value (u32), id (u16), commit marker (u16, 0 once committed)
+    fn read(page: &[u8], index: usize) -> Self {
+        let record = &page[HEADER_SIZE + index * RECORD_SIZE..][..RECORD_SIZE];
+        if record.iter().all(|&b| b == 0xFF) {
+            Slot::Free
+        } else if record[6..] != [0, 0] {
+            Slot::Invalid
+        } else {
+            Slot::Record {
+                id: u16::from_le_bytes([record[4], record[5]]),
+                value: word(record, 0),
+            }
+        }
+    }
+}
+
+fn word(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Set of variable ids, one bit per id
+struct IdSet([u32; 0x1_0000 / 32]);
+
+impl IdSet {
+    fn new() -> Self {
+        IdSet([0; 0x1_0000 / 32])
+    }
+
+    /// Adds `id`, returns false if it was already in the set
+    fn insert(&mut self, id: u16) -> bool {
+        let (word, bit) = (usize::from(id / 32), 1 << (id % 32));
+        let added = self.0[word] & bit == 0;
+        self.0[word] |= bit;
+        added
+    }
+}
+
+/// Emulated EEPROM
+pub struct Eeprom<P> {
+    pages: P,
+    /// The valid page
+    active: usize,
+    /// First slot of the active page after the last one used
+    next_slot: usize,
+}
+
+impl<P: Pages> Eeprom<P> {
+    /// Opens the EEPROM, completing an operation interrupted by a power loss
+    ///
+    /// Pages that don't hold a valid EEPROM are formatted.
+    pub fn new(pages: P) -> Result<Self, Error<P::Error>> {
+        let mut eeprom = Eeprom {
+            pages,
+            active: 0,
+            next_slot: 0,
+        };
+
+        let states = [
+            State::of(eeprom.pages.read(0)),
+            State::of(eeprom.pages.read(1)),
+        ];
+        match states {
+            [State::Valid, State::Erased] => eeprom.active = 0,
+            [State::Erased, State::Valid] => eeprom.active = 1,
+            // Interrupted while copying the variables or erasing the old page
+            [State::Valid, State::Receiving] => eeprom.finish_transfer(0, 1)?,
+            [State::Receiving, State::Valid] => eeprom.finish_transfer(1, 0)?,
+            // Interrupted before marking the new page valid
+            [State::Erased, State::Receiving] => eeprom.set_valid(1)?,
+            [State::Receiving, State::Erased] => eeprom.set_valid(0)?,
+            // Interrupted while erasing the old page, once all the variables were copied
+            [State::Corrupt, State::Receiving] => {
+                eeprom.pages.erase(0)?;
+                eeprom.set_valid(1)?;
+            }
+            [State::Receiving, State::Corrupt] => {
+                eeprom.pages.erase(1)?;
+                eeprom.set_valid(0)?;
+            }
+            [State::Valid, State::Corrupt] => {
+                eeprom.pages.erase(1)?;
+                eeprom.active = 0;
+            }
+            [State::Corrupt, State::Valid] => {
+                eeprom.pages.erase(0)?;
+                eeprom.active = 1;
+            }
+            _ => eeprom.format()?,
+        }
+        eeprom.next_slot = eeprom.next_slot(eeprom.active);
+
+        Ok(eeprom)
+    }
+
+    /// Erases all variables
+    pub fn format(&mut self) -> Result<(), Error<P::Error>> {
+        self.pages.erase(0)?;
+        self.pages.erase(1)?;
+        self.set_valid(0)?;
+        self.next_slot = 0;
+        Ok(())
+    }
+
+    /// Returns the number of variables a page can hold
+    pub fn capacity(&self) -> usize {
+        (self.pages.page_size() - HEADER_SIZE) / RECORD_SIZE
+    }
+
+    /// Returns the value of variable `id`, if it was written
+    pub fn read(&self, id: u16) -> Option<u32> {
+        self.latest(self.active, id).map(|(_, value)| value)
+    }
+
+    /// Writes variable `id`, the flash is not touched if it already has this value
+    pub fn write(&mut self, id: u16, value: u32) -> Result<(), Error<P::Error>> {
+        if self.read(id) == Some(value) {
+            return Ok(());
+        }
+
+        if self.next_slot < self.capacity() {
+            let slot = self.next_slot;
+            self.next_slot += 1;
+            self.append(self.active, slot, id, value)
+        } else {
+            self.transfer(id, value)
+        }
+    }
+
+    /// Releases the pages
+    pub fn release(self) -> P {
+        self.pages
+    }
+
+    /// Writes `id` to the other page, then copies the latest value of the other variables there
+    fn transfer(&mut self, id: u16, value: u32) -> Result<(), Error<P::Error>> {
+        let (from, to) = (self.active, 1 - self.active);
+
+        let mut ids = IdSet::new();
+        ids.insert(id);
+        let mut count = 1;
+        for (other, _) in self.records(from) {
+            if ids.insert(other) {
+                count += 1;
+            }
+        }
+        if count > self.capacity() {
+            return Err(Error::Full);
+        }
+
+        // An interrupted erase can leave data behind an erased header
+        if self.pages.read(to).iter().any(|&b| b != 0xFF) {
+            self.pages.erase(to)?;
+        }
+        self.pages.program(to, 0, &RECEIVING.to_le_bytes())?;
+        self.append(to, 0, id, value)?;
+
+        self.finish_transfer(from, to)
+    }
+
+    /// Copies the variables of `from` missing from `to`, erases `from` and marks `to` valid
+    fn finish_transfer(&mut self, from: usize, to: usize) -> Result<(), Error<P::Error>> {
+        // The records of `to` are newer than any record of `from`
+        let mut ids = IdSet::new();
+        for (id, _) in self.records(to) {
+            ids.insert(id);
+        }
+
+        let mut slot = self.next_slot(to);
+        for index in (0..self.capacity()).rev() {
+            let (id, value) = match Slot::read(self.pages.read(from), index) {
+                Slot::Record { id, value } => (id, value),
+                _ => continue,
+            };
+            // Only the last record of each variable is copied
+            if !ids.insert(id) {
+                continue;
+            }
+            if slot == self.capacity() {
+                return Err(Error::Full);
+            }
+            self.append(to, slot, id, value)?;
+            slot += 1;
+        }
+
+        self.pages.erase(from)?;
+        self.set_valid(to)?;
+        self.next_slot = slot;
+        Ok(())
+    }
+
+    fn set_valid(&mut self, page: usize) -> Result<(), Error<P::Error>> {
+        self.pages.program(page, 0, &VALID.to_le_bytes())?;
+        self.active = page;
+        Ok(())
+    }
+
+    /// Programs a record, its data first and then its commit marker
+    fn append(
+        &mut self,
+        page: usize,
+        slot: usize,
+        id: u16,
+        value: u32,
+    ) -> Result<(), Error<P::Error>> {
+        let offset = HEADER_SIZE + slot * RECORD_SIZE;
+        let value = value.to_le_bytes();
+        let id = id.to_le_bytes();
+        let data = [value[0], value[1], value[2], value[3], id[0], id[1]];
+        self.pages.program(page, offset, &data)?;
+        self.pages.program(page, offset + 6, &[0, 0])?;
+        Ok(())
+    }
+
+    /// Returns the slot index and value of the last record of `id` in `page`
+    fn latest(&self, page: usize, id: u16) -> Option<(usize, u32)> {
+        let data = self.pages.read(page);
+        (0..self.capacity())
+            .rev()
+            .find_map(|index| match Slot::read(data, index) {
+                Slot::Record { id: other, value } if other == id => Some((index, value)),
+                _ => None,
+            })
+    }
+
+    /// Returns the id and value of the committed records of `page`
+    fn records(&self, page: usize) -> impl Iterator<Item = (u16, u32)> + '_ {
+        let data = self.pages.read(page);
+        (0..self.capacity()).filter_map(move |index| match Slot::read(data, index) {
+            Slot::Record { id, value } => Some((id, value)),
+            _ => None,
+        })
+    }
+
+    /// Returns the slot after the last one used in `page`
+    fn next_slot(&self, page: usize) -> usize {
+        let data = self.pages.read(page);
+        (0..self.capacity())
+            .rev()
+            .find(|&index| Slot::read(data, index) != Slot::Free)
+            .map_or(0, |index| index + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_SIZE: usize = 128;
+
+    #[derive(Debug, PartialEq)]
+    struct PowerCut;
+
+    /// Two pages of RAM that lose power after a given number of bytes are written
+    struct RamPages {
+        pages: [[u8; PAGE_SIZE]; 2],
+        budget: Option<usize>,
+        /// Source of the bytes left in the header by an interrupted erase
+        noise: u32,
+    }
+
+    impl RamPages {
+        fn new() -> Self {
+            RamPages {
+                pages: [[0xFF; PAGE_SIZE]; 2],
+                budget: None,
+                noise: 1,
+            }
+        }
+
+        /// Spends a byte of the budget, returns false once it is exhausted
+        fn spend(&mut self) -> bool {
+            match self.budget {
+                Some(0) => false,
+                Some(ref mut left) => {
+                    *left -= 1;
+                    true
+                }
+                None => true,
+            }
+        }
+    }
+
+    impl Pages for RamPages {
+        type Error = PowerCut;
+
+        fn page_size(&self) -> usize {
+            PAGE_SIZE
+        }
+
+        fn read(&self, page: usize) -> &[u8] {
+            &self.pages[page]
+        }
+
+        fn erase(&mut self, page: usize) -> Result<(), PowerCut> {
+            if self.spend() {
+                self.pages[page] = [0xFF; PAGE_SIZE];
+                Ok(())
+            } else {
+                // Only the start of the page was erased, and the header holds arbitrary bytes
+                self.pages[page][..PAGE_SIZE / 2].copy_from_slice(&[0xFF; PAGE_SIZE / 2]);
+                for byte in &mut self.pages[page][..HEADER_SIZE] {
+                    self.noise ^= self.noise << 13;
+                    self.noise ^= self.noise >> 17;
+                    self.noise ^= self.noise << 5;
+                    *byte = self.noise as u8;
+                }
+                Err(PowerCut)
+            }
+        }
+
+        fn program(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), PowerCut> {
+            for (i, byte) in data.iter().enumerate() {
+                if !self.spend() {
+                    return Err(PowerCut);
+                }
+                self.pages[page][offset + i] &= byte;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn formats_blank_pages() {
+        let eeprom = Eeprom::new(RamPages::new()).unwrap();
+        assert_eq!(eeprom.capacity(), 15);
+        assert_eq!(eeprom.read(0), None);
+
+        let pages = eeprom.release();
+        assert_eq!(State::of(&pages.pages[0]), State::Valid);
+        assert_eq!(State::of(&pages.pages[1]), State::Erased);
+    }
+
+    #[test]
+    fn write_read_and_transfer() {
+        let mut eeprom = Eeprom::new(RamPages::new()).unwrap();
+        for n in 0..100 {
+            eeprom.write(n % 5, u32::from(n)).unwrap();
+            eeprom.write(1000, 42).unwrap();
+        }
+        for id in 0..5 {
+            assert_eq!(eeprom.read(id), Some(95 + u32::from(id)));
+        }
+        assert_eq!(eeprom.read(1000), Some(42));
+        assert_eq!(eeprom.read(5), None);
+
+        // The values survive a restart
+        let eeprom = Eeprom::new(eeprom.release()).unwrap();
+        assert_eq!(eeprom.read(4), Some(99));
+        assert_eq!(eeprom.read(1000), Some(42));
+    }
+
+    #[test]
+    fn full() {
+        let mut eeprom = Eeprom::new(RamPages::new()).unwrap();
+        for id in 0..15 {
+            eeprom.write(id, 0).unwrap();
+        }
+        assert_eq!(eeprom.write(15, 0), Err(Error::Full));
+        // Existing variables can still be updated
+        eeprom.write(3, 3).unwrap();
+        assert_eq!(eeprom.read(3), Some(3));
+        assert_eq!(eeprom.read(14), Some(0));
+    }
+
+    #[test]
+    fn corrupt_old_page() {
+        let mut eeprom = Eeprom::new(RamPages::new()).unwrap();
+        eeprom.write(1, 10).unwrap();
+        eeprom.write(2, 20).unwrap();
+        let mut pages = eeprom.release();
+
+        // All the variables were copied to the new page, and the erase of the old one was
+        // interrupted
+        let mut receiving = pages.pages[0];
+        receiving[..4].copy_from_slice(&RECEIVING.to_le_bytes());
+        let mut corrupt = pages.pages[0];
+        corrupt[..4].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+
+        for &(old, new) in [(0, 1), (1, 0)].iter() {
+            let mut pages = RamPages::new();
+            pages.pages[old] = corrupt;
+            pages.pages[new] = receiving;
+
+            let eeprom = Eeprom::new(pages).unwrap();
+            assert_eq!(eeprom.read(1), Some(10));
+            assert_eq!(eeprom.read(2), Some(20));
+
+            let pages = eeprom.release();
+            assert_eq!(State::of(&pages.pages[old]), State::Erased);
+            assert_eq!(State::of(&pages.pages[new]), State::Valid);
+        }
+    }
+
+    #[test]
+    fn power_cuts() {
+        const IDS: usize = 6;
+        let value = |n: usize| (n as u32).wrapping_mul(0x9E37_79B9);
+
+        // Every number of bytes the write sequence can get through, until it completes
+        for cut in 0.. {
+            let mut pages = RamPages::new();
+            let mut expected = [None; IDS];
+            pages.budget = Some(cut);
+            pages.noise = cut as u32 + 1;
+
+            let mut interrupted = None;
+            match Eeprom::new(pages) {
+                Err(_) => {
+                    // Power lost while formatting, nothing written yet
+                    continue;
+                }
+                Ok(mut eeprom) => {
+                    for n in 0..80 {
+                        let id = n * 7 % IDS;
+                        match eeprom.write(id as u16, value(n)) {
+                            Ok(()) => expected[id] = Some(value(n)),
+                            Err(Error::Flash(PowerCut)) => {
+                                interrupted = Some((id, value(n)));
+                                break;
+                            }
+                            Err(Error::Full) => panic!("full"),
+                        }
+                    }
+                    pages = eeprom.release();
+                }
+            }
+
+            pages.budget = None;
+            let eeprom = Eeprom::new(pages).unwrap();
+            for (id, &old) in expected.iter().enumerate() {
+                let read = eeprom.read(id as u16);
+                match interrupted {
+                    Some((cut_id, new)) if cut_id == id => {
+                        assert!(read == old || read == Some(new), "cut {}", cut)
+                    }
+                    _ => assert_eq!(read, old, "cut {}", cut),
+                }
+            }
+
+            if interrupted.is_none() {
+                break;
+            }
+        }
+    }
+}
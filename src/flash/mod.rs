@@ -0,0 +1,950 @@
+//! Internal flash memory
+//!
+//! The flash is divided into sectors of 16, 64 and 128 KB, which are the smallest units that can
+//! be erased. The 2 MB devices, and the 1 MB F42x/F43x/F469/F479 with the DB1M option bit set,
+//! split it into two banks with the same layout.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{flash::Flash, rcc::VddRange, stm32};
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let mut flash = Flash::new(dp.FLASH, VddRange::V2_7ToV3_6);
+//!
+//! // Sector 7 of a 512 KB device
+//! let offset = 0x6_0000;
+//! {
+//!     let mut unlocked = flash.unlocked();
+//!     unlocked.erase(offset).unwrap();
+//!     unlocked.program(offset, b"config").unwrap();
+//! }
+//! assert_eq!(&flash.read()[offset..offset + 6], b"config");
+//! ```
+
+use crate::rcc::VddRange;
+use crate::signature::FlashSize;
+use crate::stm32::FLASH;
+
+pub mod eeprom;
+mod otp;
+
+pub use otp::{Otp, OtpError};
+
+#[cfg(any(
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+mod dual_bank;
+#[cfg(any(
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+pub use dual_bank::{Bank, DualBank};
+
+/// Address of the start of the flash memory
+pub const FLASH_START: usize = 0x0800_0000;
+
+const KEY1: u32 = 0x4567_0123;
+const KEY2: u32 = 0xCDEF_89AB;
+
+/// Flash operation error
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// PGSERR: the programming sequence was wrong, for example PG was not set
+    ProgrammingSequence,
+    /// PGPERR: the programming parallelism does not match the data size
+    Parallelism,
+    /// PGAERR: the data is not aligned to the programming parallelism
+    Alignment,
+    /// WRPERR: the sector is write protected
+    WriteProtection,
+    /// OPERR: the operation could not be completed
+    Operation,
+    /// The address is outside of the flash memory
+    OutOfBounds,
+}
+
+impl Error {
+    /// Decodes the error flags of FLASH_SR
+    fn from_sr(sr: u32) -> Option<Self> {
+        if sr & (1 << 7) != 0 {
+            Some(Error::ProgrammingSequence)
+        } else if sr & (1 << 6) != 0 {
+            Some(Error::Parallelism)
+        } else if sr & (1 << 5) != 0 {
+            Some(Error::Alignment)
+        } else if sr & (1 << 4) != 0 {
+            Some(Error::WriteProtection)
+        } else if sr & (1 << 1) != 0 {
+            Some(Error::Operation)
+        } else {
+            None
+        }
+    }
+}
+
+/// Error flags of FLASH_SR, cleared by writing ones
+const SR_ERRORS: u32 = 0b1111_0010;
+
+/// A flash sector
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlashSector {
+    /// Sector number, as used in the reference manual. The sectors of the second bank are
+    /// numbered from 12.
+    pub number: u8,
+    /// Offset from the start of the flash memory
+    pub offset: usize,
+    /// Size in bytes
+    pub size: usize,
+}
+
+impl FlashSector {
+    /// Returns true if the sector contains the byte at `offset`
+    pub fn contains(&self, offset: usize) -> bool {
+        self.offset <= offset && offset < self.offset + self.size
+    }
+}
+
+/// Sector layout of the flash memory
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SectorLayout {
+    /// Size of a bank, in bytes
+    bank_size: usize,
+    dual_bank: bool,
+}
+
+impl SectorLayout {
+    /// Returns the layout of a flash of `flash_size`, with `dual_bank_1m` the value of the DB1M
+    /// option bit on a 1 MB device that has it.
+    pub fn new(flash_size: &FlashSize, dual_bank_1m: bool) -> Self {
+        let size = flash_size.bytes();
+        let dual_bank = size == 2048 * 1024 || (size == 1024 * 1024 && dual_bank_1m);
+        SectorLayout {
+            bank_size: if dual_bank { size / 2 } else { size },
+            dual_bank,
+        }
+    }
+
+    /// Returns the size of the flash memory in bytes
+    pub fn size(&self) -> usize {
+        if self.dual_bank {
+            2 * self.bank_size
+        } else {
+            self.bank_size
+        }
+    }
+
+    /// Returns true if the flash is split into two banks
+    pub fn is_dual_bank(&self) -> bool {
+        self.dual_bank
+    }
+
+    /// Returns the sector that contains the byte at `offset` from the start of the flash memory
+    pub fn sector(&self, offset: usize) -> Option<FlashSector> {
+        if offset >= self.size() {
+            return None;
+        }
+        let bank = offset / self.bank_size;
+        let bank_offset = offset % self.bank_size;
+
+        const KB: usize = 1024;
+        let (index, start, size) = match bank_offset {
+            0..=0xFFFF => (
+                bank_offset / (16 * KB),
+                bank_offset & !(16 * KB - 1),
+                16 * KB,
+            ),
+            0x1_0000..=0x1_FFFF => (4, 64 * KB, 64 * KB),
+            _ => (
+                4 + bank_offset / (128 * KB),
+                bank_offset & !(128 * KB - 1),
+                128 * KB,
+            ),
+        };
+
+        Some(FlashSector {
+            number: (12 * bank + index) as u8,
+            offset: bank * self.bank_size + start,
+            size,
+        })
+    }
+
+    /// Value of the SNB field that selects `sector` for erasing
+    fn snb(&self, sector: &FlashSector) -> u8 {
+        if self.dual_bank && sector.number >= 12 {
+            0b1_0000 | (sector.number - 12)
+        } else {
+            sector.number
+        }
+    }
+
+    /// Returns an iterator over all sectors, in address order
+    pub fn sectors(&self) -> impl Iterator<Item = FlashSector> {
+        let layout = *self;
+        let mut offset = 0;
+        core::iter::from_fn(move || {
+            let sector = layout.sector(offset)?;
+            offset = sector.offset + sector.size;
+            Some(sector)
+        })
+    }
+}
+
+/// Flash programming parallelism
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Psize {
+    X8 = 0b00,
+    X16 = 0b01,
+    X32 = 0b10,
+}
+
+impl Psize {
+    /// Largest parallelism the supply voltage allows, x64 needs an external VPP
+    fn from_vdd(vdd: VddRange) -> Self {
+        match vdd {
+            VddRange::V1_8ToV2_1 => Psize::X8,
+            VddRange::V2_1ToV2_4 | VddRange::V2_4ToV2_7 => Psize::X16,
+            VddRange::V2_7ToV3_6 => Psize::X32,
+        }
+    }
+
+    fn bytes(self) -> usize {
+        1 << self as usize
+    }
+}
+
+const OPTKEY1: u32 = 0x0819_2A3B;
+const OPTKEY2: u32 = 0x4C5D_6E7F;
+
+// Fields of FLASH_OPTCR, the PAC is missing some of them on some devices
+const OPTCR_BOR_LEV: u32 = 0b11 << 2;
+#[cfg(any(
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+const OPTCR_BFB2: u32 = 1 << 4;
+const OPTCR_WDG_SW: u32 = 1 << 5;
+const OPTCR_NRST_STOP: u32 = 1 << 6;
+const OPTCR_NRST_STDBY: u32 = 1 << 7;
+const OPTCR_RDP: u32 = 0xFF << 8;
+const OPTCR_NWRP: u32 = 0xFFF << 16;
+#[cfg(any(
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+const OPTCR_DB1M: u32 = 1 << 30;
+#[cfg(not(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417"
+)))]
+const OPTCR_SPRMOD: u32 = 1 << 31;
+
+/// Readout protection level
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReadProtection {
+    /// No protection
+    Level0,
+    /// The flash can't be read by the debugger or by code booted from SRAM or system memory.
+    /// Going back to level 0 mass erases the flash.
+    Level1,
+    /// Debugging and booting from SRAM or system memory are disabled and the option bytes are
+    /// frozen. **This is permanent**, the device can't be unprotected anymore.
+    Level2,
+}
+
+impl ReadProtection {
+    fn from_bits(rdp: u8) -> Self {
+        match rdp {
+            0xAA => ReadProtection::Level0,
+            0xCC => ReadProtection::Level2,
+            _ => ReadProtection::Level1,
+        }
+    }
+
+    fn to_bits(self) -> u8 {
+        match self {
+            ReadProtection::Level0 => 0xAA,
+            ReadProtection::Level1 => 0x55,
+            ReadProtection::Level2 => 0xCC,
+        }
+    }
+}
+
+/// Brown-out reset threshold
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BorLevel {
+    /// Reset below about 2.7 V
+    Level3 = 0b00,
+    /// Reset below about 2.4 V
+    Level2 = 0b01,
+    /// Reset below about 2.1 V
+    Level1 = 0b10,
+    /// Only the power-on/power-down reset, below about 1.8 V
+    Off = 0b11,
+}
+
+impl BorLevel {
+    fn from_bits(bits: u32) -> Self {
+        match bits & 0b11 {
+            0b00 => BorLevel::Level3,
+            0b01 => BorLevel::Level2,
+            0b10 => BorLevel::Level1,
+            _ => BorLevel::Off,
+        }
+    }
+}
+
+/// Meaning of the per-sector protection bits
+#[cfg(not(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417"
+)))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProtectionMode {
+    /// The protected sectors can't be erased or programmed
+    Write,
+    /// The protected sectors can only be fetched as code, not read as data (PCROP). Going back
+    /// to write protection needs a change from read protection level 1 to level 0.
+    Pcrop,
+}
+
+/// User option bytes, read with [`Flash::option_bytes`] and written with
+/// [`Flash::commit_option_bytes`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OptionBytes {
+    /// RDP: readout protection level
+    pub read_protection: ReadProtection,
+    /// BOR_LEV: brown-out reset threshold
+    pub bor_level: BorLevel,
+    /// WDG_SW: the independent watchdog is started by software, instead of at reset
+    pub software_watchdog: bool,
+    /// Inverse of nRST_STOP: entering Stop mode resets the device
+    pub reset_on_stop: bool,
+    /// Inverse of nRST_STDBY: entering Standby mode resets the device
+    pub reset_on_standby: bool,
+    /// nWRP: protected sectors, bit `n` for sector `n`
+    pub protected_sectors: u32,
+    /// SPRMOD: protection of the sectors in `protected_sectors`
+    #[cfg(not(any(
+        feature = "stm32f405",
+        feature = "stm32f407",
+        feature = "stm32f415",
+        feature = "stm32f417"
+    )))]
+    pub protection_mode: ProtectionMode,
+    /// BFB2: boot from bank 2 when it holds a valid vector table
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    pub boot_from_bank2: bool,
+    /// DB1M: split a 1 MB flash into two banks
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    pub dual_bank_1m: bool,
+}
+
+impl OptionBytes {
+    /// Decodes FLASH_OPTCR, and FLASH_OPTCR1 for the sectors of the second bank
+    fn from_bits(optcr: u32, optcr1: u32) -> Self {
+        #[cfg(not(any(
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417"
+        )))]
+        let pcrop = optcr & OPTCR_SPRMOD != 0;
+        #[cfg(any(
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417"
+        ))]
+        let pcrop = false;
+
+        // nWRP bits are cleared for write protected sectors but set for PCROP sectors
+        let nwrp = ((optcr & OPTCR_NWRP) >> 16) | ((optcr1 & OPTCR_NWRP) >> 4);
+        let protected_sectors = if pcrop { nwrp } else { !nwrp & 0xFF_FFFF };
+
+        OptionBytes {
+            read_protection: ReadProtection::from_bits(((optcr & OPTCR_RDP) >> 8) as u8),
+            bor_level: BorLevel::from_bits(optcr >> 2),
+            software_watchdog: optcr & OPTCR_WDG_SW != 0,
+            reset_on_stop: optcr & OPTCR_NRST_STOP == 0,
+            reset_on_standby: optcr & OPTCR_NRST_STDBY == 0,
+            protected_sectors: protected_sectors & Self::sector_mask(),
+            #[cfg(not(any(
+                feature = "stm32f405",
+                feature = "stm32f407",
+                feature = "stm32f415",
+                feature = "stm32f417"
+            )))]
+            protection_mode: if pcrop {
+                ProtectionMode::Pcrop
+            } else {
+                ProtectionMode::Write
+            },
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            boot_from_bank2: optcr & OPTCR_BFB2 != 0,
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            dual_bank_1m: optcr & OPTCR_DB1M != 0,
+        }
+    }
+
+    /// Encodes the option bytes into `optcr` and `optcr1`, keeping their other bits
+    fn to_bits(self, mut optcr: u32, mut optcr1: u32) -> (u32, u32) {
+        #[cfg(not(any(
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417"
+        )))]
+        let pcrop = self.protection_mode == ProtectionMode::Pcrop;
+        #[cfg(any(
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417"
+        ))]
+        let pcrop = false;
+
+        let sectors = self.protected_sectors & Self::sector_mask();
+        let nwrp = if pcrop { sectors } else { !sectors };
+
+        optcr &= !(OPTCR_BOR_LEV
+            | OPTCR_WDG_SW
+            | OPTCR_NRST_STOP
+            | OPTCR_NRST_STDBY
+            | OPTCR_RDP
+            | OPTCR_NWRP);
+        optcr |= (self.bor_level as u32) << 2;
+        if self.software_watchdog {
+            optcr |= OPTCR_WDG_SW;
+        }
+        if !self.reset_on_stop {
+            optcr |= OPTCR_NRST_STOP;
+        }
+        if !self.reset_on_standby {
+            optcr |= OPTCR_NRST_STDBY;
+        }
+        optcr |= u32::from(self.read_protection.to_bits()) << 8;
+        optcr |= (nwrp << 16) & OPTCR_NWRP;
+
+        optcr1 &= !OPTCR_NWRP;
+        optcr1 |= (nwrp << 4) & OPTCR_NWRP;
+
+        #[cfg(not(any(
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417"
+        )))]
+        {
+            optcr &= !OPTCR_SPRMOD;
+            if pcrop {
+                optcr |= OPTCR_SPRMOD;
+            }
+        }
+
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        {
+            optcr &= !(OPTCR_BFB2 | OPTCR_DB1M);
+            if self.boot_from_bank2 {
+                optcr |= OPTCR_BFB2;
+            }
+            if self.dual_bank_1m {
+                optcr |= OPTCR_DB1M;
+            }
+        }
+
+        (optcr, optcr1)
+    }
+
+    /// Sectors that have a protection bit, the second bank's are in FLASH_OPTCR1
+    fn sector_mask() -> u32 {
+        if cfg!(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        )) {
+            0xFF_FFFF
+        } else {
+            0xFFF
+        }
+    }
+}
+
+/// Internal flash memory
+pub struct Flash {
+    flash: FLASH,
+    layout: SectorLayout,
+    psize: Psize,
+}
+
+impl Flash {
+    /// Wraps the flash interface, `vdd` is the supply voltage range, which limits the number of
+    /// bytes programmed at once
+    pub fn new(flash: FLASH, vdd: VddRange) -> Self {
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        let dual_bank_1m = flash.optcr.read().bits() & OPTCR_DB1M != 0;
+        #[cfg(not(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        )))]
+        let dual_bank_1m = false;
+
+        Flash {
+            flash,
+            layout: SectorLayout::new(FlashSize::get(), dual_bank_1m),
+            psize: Psize::from_vdd(vdd),
+        }
+    }
+
+    /// Returns the sector layout
+    pub fn layout(&self) -> &SectorLayout {
+        &self.layout
+    }
+
+    /// Returns the contents of the flash memory
+    pub fn read(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(FLASH_START as *const u8, self.layout.size()) }
+    }
+
+    /// Unlocks the flash for erasing and programming, it is locked again when the returned value
+    /// is dropped
+    pub fn unlocked(&mut self) -> UnlockedFlash<'_> {
+        if self.flash.cr.read().lock().bit_is_set() {
+            // some chip variants declare `.bits()` as unsafe, some don't
+            #[allow(unused_unsafe)]
+            self.flash.keyr.write(|w| unsafe { w.key().bits(KEY1) });
+            #[allow(unused_unsafe)]
+            self.flash.keyr.write(|w| unsafe { w.key().bits(KEY2) });
+        }
+        UnlockedFlash { flash: self }
+    }
+
+    /// Returns the one-time programmable area
+    pub fn otp(&mut self) -> Otp<'_> {
+        Otp { flash: self }
+    }
+
+    /// Reads the user option bytes
+    pub fn option_bytes(&self) -> OptionBytes {
+        let (optcr, optcr1) = self.read_optcr();
+        OptionBytes::from_bits(optcr, optcr1)
+    }
+
+    /// Programs the user option bytes
+    ///
+    /// The device loads the option bytes at reset, so most of them only take effect after the
+    /// next one. Lowering the read protection from level 1 to level 0 mass erases the flash,
+    /// including the running program, and setting level 2 can't be undone.
+    pub fn commit_option_bytes(&mut self, option_bytes: &OptionBytes) -> Result<(), Error> {
+        let regs = &self.flash;
+        while regs.sr.read().bsy().bit_is_set() {}
+        if regs.optcr.read().optlock().bit_is_set() {
+            // some chip variants declare `.bits()` as unsafe, some don't
+            #[allow(unused_unsafe)]
+            regs.optkeyr.write(|w| unsafe { w.optkey().bits(OPTKEY1) });
+            #[allow(unused_unsafe)]
+            regs.optkeyr.write(|w| unsafe { w.optkey().bits(OPTKEY2) });
+        }
+        regs.sr.write(|w| unsafe { w.bits(SR_ERRORS) });
+
+        let (optcr, optcr1) = self.read_optcr();
+        let (optcr, _optcr1) = option_bytes.to_bits(optcr, optcr1);
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        regs.optcr1.write(|w| unsafe { w.bits(_optcr1) });
+        regs.optcr.write(|w| unsafe { w.bits(optcr) });
+        regs.optcr.modify(|_, w| w.optstrt().set_bit());
+
+        while regs.sr.read().bsy().bit_is_set() {}
+        let result = match Error::from_sr(regs.sr.read().bits()) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        };
+        regs.optcr.modify(|_, w| w.optlock().set_bit());
+
+        result
+    }
+
+    /// Reads FLASH_OPTCR and, on the devices that have it, FLASH_OPTCR1
+    fn read_optcr(&self) -> (u32, u32) {
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        let optcr1 = self.flash.optcr1.read().bits();
+        #[cfg(not(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        )))]
+        let optcr1 = 0;
+
+        (self.flash.optcr.read().bits(), optcr1)
+    }
+
+    /// Releases the FLASH peripheral
+    pub fn release(self) -> FLASH {
+        self.flash
+    }
+}
+
+/// Unlocked flash memory, locked again when dropped
+pub struct UnlockedFlash<'a> {
+    flash: &'a mut Flash,
+}
+
+impl Drop for UnlockedFlash<'_> {
+    fn drop(&mut self) {
+        self.flash.flash.cr.modify(|_, w| w.lock().set_bit());
+    }
+}
+
+impl UnlockedFlash<'_> {
+    /// Erases the sector that contains the byte at `offset`, and returns it
+    ///
+    /// The CPU stalls while the sector is erased if code runs from the same bank, which takes up
+    /// to 2 s for a 128 KB sector.
+    pub fn erase(&mut self, offset: usize) -> Result<FlashSector, Error> {
+        let sector = self.flash.layout.sector(offset).ok_or(Error::OutOfBounds)?;
+        let snb = self.flash.layout.snb(&sector);
+        let psize = self.flash.psize as u8;
+
+        self.start();
+        let regs = &self.flash.flash;
+        regs.cr.modify(|_, w| unsafe {
+            w.ser().set_bit();
+            w.snb().bits(snb);
+            w.psize().bits(psize)
+        });
+        regs.cr.modify(|_, w| w.strt().set_bit());
+        let result = self.wait();
+        self.flash.flash.cr.modify(|_, w| w.ser().clear_bit());
+        flush_caches(&self.flash.flash);
+
+        result.map(|_| sector)
+    }
+
+    /// Programs `data` at `offset`, the destination must have been erased
+    ///
+    /// The data is written with the largest parallelism the supply voltage allows, the
+    /// unaligned bytes at its start and end are written one by one.
+    pub fn program(&mut self, offset: usize, data: &[u8]) -> Result<(), Error> {
+        if offset
+            .checked_add(data.len())
+            .map_or(true, |end| end > self.flash.layout.size())
+        {
+            return Err(Error::OutOfBounds);
+        }
+
+        self.program_at(FLASH_START + offset, data)
+    }
+
+    /// Programs `data` at `address`, in the flash memory or the OTP area
+    fn program_at(&mut self, address: usize, data: &[u8]) -> Result<(), Error> {
+        self.start();
+        let result = self.program_unchecked(address, data);
+        self.flash.flash.cr.modify(|_, w| w.pg().clear_bit());
+        flush_caches(&self.flash.flash);
+
+        result
+    }
+
+    fn program_unchecked(&mut self, mut address: usize, mut data: &[u8]) -> Result<(), Error> {
+        let wide = self.flash.psize;
+
+        while !data.is_empty() {
+            let psize = if address % wide.bytes() == 0 && data.len() >= wide.bytes() {
+                wide
+            } else {
+                Psize::X8
+            };
+            let regs = &self.flash.flash;
+            #[allow(unused_unsafe)]
+            regs.cr
+                .modify(|_, w| unsafe { w.pg().set_bit().psize().bits(psize as u8) });
+
+            let (chunk, rest) = data.split_at(psize.bytes());
+            unsafe {
+                match psize {
+                    Psize::X8 => core::ptr::write_volatile(address as *mut u8, chunk[0]),
+                    Psize::X16 => core::ptr::write_volatile(
+                        address as *mut u16,
+                        u16::from_le_bytes([chunk[0], chunk[1]]),
+                    ),
+                    Psize::X32 => core::ptr::write_volatile(
+                        address as *mut u32,
+                        u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]),
+                    ),
+                }
+            }
+            self.wait()?;
+
+            address += psize.bytes();
+            data = rest;
+        }
+        Ok(())
+    }
+
+    /// Waits for a previous operation and clears the error flags it left
+    fn start(&mut self) {
+        let regs = &self.flash.flash;
+        while regs.sr.read().bsy().bit_is_set() {}
+        regs.sr.write(|w| unsafe { w.bits(SR_ERRORS) });
+    }
+
+    /// Waits for the current operation and returns its error
+    fn wait(&mut self) -> Result<(), Error> {
+        let regs = &self.flash.flash;
+        while regs.sr.read().bsy().bit_is_set() {}
+        match Error::from_sr(regs.sr.read().bits()) {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Resets the instruction and data caches, which may hold the previous contents of the flash
+fn flush_caches(flash: &FLASH) {
+    let acr = flash.acr.read();
+    let (icen, dcen) = (acr.icen().bit(), acr.dcen().bit());
+
+    // The caches can only be reset while they are disabled
+    flash
+        .acr
+        .modify(|_, w| w.icen().clear_bit().dcen().clear_bit());
+    flash
+        .acr
+        .modify(|_, w| w.icrst().set_bit().dcrst().set_bit());
+    flash
+        .acr
+        .modify(|_, w| w.icrst().clear_bit().dcrst().clear_bit());
+    flash.acr.modify(|_, w| w.icen().bit(icen).dcen().bit(dcen));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KB: usize = 1024;
+
+    fn layout_of(kilo_bytes: u16, dual_bank_1m: bool) -> SectorLayout {
+        SectorLayout::new(&FlashSize(kilo_bytes), dual_bank_1m)
+    }
+
+    fn sector(number: u8, offset: usize, size: usize) -> Option<FlashSector> {
+        Some(FlashSector {
+            number,
+            offset,
+            size,
+        })
+    }
+
+    #[test]
+    fn single_bank() {
+        let layout = layout_of(512, false);
+        assert!(!layout.is_dual_bank());
+        assert_eq!(layout.size(), 512 * KB);
+        assert_eq!(layout.sector(0), sector(0, 0, 16 * KB));
+        assert_eq!(layout.sector(0x7FFF), sector(1, 0x4000, 16 * KB));
+        assert_eq!(layout.sector(0xC000), sector(3, 0xC000, 16 * KB));
+        assert_eq!(layout.sector(0x1_2345), sector(4, 0x1_0000, 64 * KB));
+        assert_eq!(layout.sector(0x2_0000), sector(5, 0x2_0000, 128 * KB));
+        assert_eq!(layout.sector(0x7_FFFF), sector(7, 0x6_0000, 128 * KB));
+        assert_eq!(layout.sector(0x8_0000), None);
+        assert_eq!(layout.sectors().count(), 8);
+    }
+
+    #[test]
+    fn small_and_large_single_bank() {
+        // STM32F410
+        let layout = layout_of(128, false);
+        assert_eq!(layout.sectors().count(), 5);
+        assert_eq!(layout.sector(0x1_FFFF), sector(4, 0x1_0000, 64 * KB));
+        assert_eq!(layout.sector(0x2_0000), None);
+
+        // STM32F413, 1.5 MB in a single bank
+        let layout = layout_of(1536, false);
+        assert!(!layout.is_dual_bank());
+        assert_eq!(layout.sectors().count(), 16);
+        assert_eq!(layout.sector(0x17_FFFF), sector(15, 0x16_0000, 128 * KB));
+        assert_eq!(layout.snb(&layout.sector(0x17_FFFF).unwrap()), 15);
+    }
+
+    #[test]
+    fn dual_bank_2m() {
+        let layout = layout_of(2048, false);
+        assert!(layout.is_dual_bank());
+        assert_eq!(layout.size(), 2048 * KB);
+        assert_eq!(layout.sectors().count(), 24);
+        assert_eq!(layout.sector(0xF_FFFF), sector(11, 0xE_0000, 128 * KB));
+        assert_eq!(layout.sector(0x10_0000), sector(12, 0x10_0000, 16 * KB));
+        assert_eq!(layout.sector(0x11_0000), sector(16, 0x11_0000, 64 * KB));
+        assert_eq!(layout.sector(0x1F_FFFF), sector(23, 0x1E_0000, 128 * KB));
+        assert_eq!(layout.sector(0x20_0000), None);
+
+        assert_eq!(layout.snb(&layout.sector(0xF_FFFF).unwrap()), 11);
+        assert_eq!(layout.snb(&layout.sector(0x10_0000).unwrap()), 0b1_0000);
+        assert_eq!(layout.snb(&layout.sector(0x1F_FFFF).unwrap()), 0b1_1011);
+    }
+
+    #[test]
+    fn dual_bank_1m() {
+        assert!(!layout_of(1024, false).is_dual_bank());
+        assert_eq!(layout_of(1024, false).sectors().count(), 12);
+
+        let layout = layout_of(1024, true);
+        assert!(layout.is_dual_bank());
+        assert_eq!(layout.sectors().count(), 16);
+        assert_eq!(layout.sector(0x7_FFFF), sector(7, 0x6_0000, 128 * KB));
+        assert_eq!(layout.sector(0x8_0000), sector(12, 0x8_0000, 16 * KB));
+        assert_eq!(layout.sector(0xF_FFFF), sector(19, 0xE_0000, 128 * KB));
+        assert_eq!(layout.snb(&layout.sector(0x8_0000).unwrap()), 0b1_0000);
+    }
+
+    #[test]
+    fn sector_contains() {
+        let sector = layout_of(512, false).sector(0x1_0000).unwrap();
+        assert!(!sector.contains(0xFFFF));
+        assert!(sector.contains(0x1_0000));
+        assert!(sector.contains(0x1_FFFF));
+        assert!(!sector.contains(0x2_0000));
+    }
+
+    #[test]
+    fn option_bytes_defaults() {
+        // Factory values: level 0, BOR off, no reset in Stop/Standby, no sector protected
+        let ob = OptionBytes::from_bits(0x0FFF_AAED, 0x0FFF_0000);
+        assert_eq!(ob.read_protection, ReadProtection::Level0);
+        assert_eq!(ob.bor_level, BorLevel::Off);
+        assert!(ob.software_watchdog);
+        assert!(!ob.reset_on_stop);
+        assert!(!ob.reset_on_standby);
+        assert_eq!(ob.protected_sectors, 0);
+        assert_eq!(
+            ob.to_bits(0x0FFF_AAED, 0x0FFF_0000),
+            (0x0FFF_AAED, 0x0FFF_0000)
+        );
+    }
+
+    #[test]
+    fn option_bytes_encoding() {
+        let mut ob = OptionBytes::from_bits(0x0FFF_AAED, 0x0FFF_0000);
+        ob.read_protection = ReadProtection::Level1;
+        ob.bor_level = BorLevel::Level3;
+        ob.reset_on_stop = true;
+        ob.protected_sectors = 0b11;
+
+        let (optcr, optcr1) = ob.to_bits(0x0FFF_AAED, 0x0FFF_0000);
+        assert_eq!(optcr & 0xFFFF, 0x55A1);
+        assert_eq!(optcr & OPTCR_NWRP, 0x0FFC_0000);
+        assert_eq!(optcr1, 0x0FFF_0000);
+        assert_eq!(OptionBytes::from_bits(optcr, optcr1), ob);
+
+        for (rdp, level) in [
+            (0xAA, ReadProtection::Level0),
+            (0x00, ReadProtection::Level1),
+            (0x55, ReadProtection::Level1),
+            (0xCC, ReadProtection::Level2),
+        ]
+        .iter()
+        {
+            assert_eq!(ReadProtection::from_bits(*rdp), *level);
+        }
+    }
+
+    #[test]
+    fn error_decoding() {
+        assert_eq!(Error::from_sr(0), None);
+        assert_eq!(Error::from_sr(1), None);
+        assert_eq!(Error::from_sr(1 << 1), Some(Error::Operation));
+        assert_eq!(Error::from_sr(1 << 4), Some(Error::WriteProtection));
+        assert_eq!(Error::from_sr(1 << 5), Some(Error::Alignment));
+        assert_eq!(Error::from_sr(1 << 6), Some(Error::Parallelism));
+        assert_eq!(Error::from_sr(1 << 7), Some(Error::ProgrammingSequence));
+        assert_eq!(
+            Error::from_sr((1 << 7) | (1 << 5)),
+            Some(Error::ProgrammingSequence)
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! One-time programmable area
+//!
+//! 512 bytes in 16 blocks of 32 bytes, followed by a lock byte per block. Bits can only be
+//! programmed once, from 1 to 0, and a locked block can't be programmed at all anymore.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{flash::Flash, rcc::VddRange, stm32};
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let mut flash = Flash::new(dp.FLASH, VddRange::V2_7ToV3_6);
+//! let mut otp = flash.otp();
+//!
+//! if !otp.is_locked(0) {
+//!     otp.program(0, 0, b"SN-000042").unwrap();
+//!     otp.lock(0).unwrap();
+//! }
+//! ```
+
+use super::{Error, Flash};
+
+const OTP_START: usize = 0x1FFF_7800;
+const LOCK_START: usize = 0x1FFF_7A00;
+
+/// OTP programming error
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OtpError {
+    /// The flash operation failed
+    Flash(Error),
+    /// The data doesn't fit in the block
+    OutOfBounds,
+    /// The block is locked
+    Locked,
+    /// Some of the bytes were already programmed
+    AlreadyProgrammed,
+}
+
+impl From<Error> for OtpError {
+    fn from(error: Error) -> Self {
+        OtpError::Flash(error)
+    }
+}
+
+/// One-time programmable area, see [`Flash::otp`]
+pub struct Otp<'a> {
+    pub(super) flash: &'a mut Flash,
+}
+
+impl Otp<'_> {
+    /// Number of blocks
+    pub const BLOCKS: usize = 16;
+    /// Size of a block in bytes
+    pub const BLOCK_SIZE: usize = 32;
+
+    /// Returns the contents of `block`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block` is not below `BLOCKS`.
+    pub fn read(&self, block: usize) -> &[u8] {
+        assert!(block < Self::BLOCKS);
+        unsafe {
+            core::slice::from_raw_parts(
+                (OTP_START + block * Self::BLOCK_SIZE) as *const u8,
+                Self::BLOCK_SIZE,
+            )
+        }
+    }
+
+    /// Returns true if `block` is locked
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block` is not below `BLOCKS`.
+    pub fn is_locked(&self, block: usize) -> bool {
+        assert!(block < Self::BLOCKS);
+        unsafe { core::ptr::read_volatile((LOCK_START + block) as *const u8) != 0xFF }
+    }
+
+    /// Programs `data` at `offset` in `block`
+    ///
+    /// All the bytes to program must still be in their erased state, 0xFF.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block` is not below `BLOCKS`.
+    pub fn program(&mut self, block: usize, offset: usize, data: &[u8]) -> Result<(), OtpError> {
+        check_program(self.read(block), self.is_locked(block), offset, data)?;
+
+        let address = OTP_START + block * Self::BLOCK_SIZE + offset;
+        self.flash.unlocked().program_at(address, data)?;
+        Ok(())
+    }
+
+    /// Locks `block`, it can't be programmed anymore
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block` is not below `BLOCKS`.
+    pub fn lock(&mut self, block: usize) -> Result<(), OtpError> {
+        if self.is_locked(block) {
+            return Ok(());
+        }
+        self.flash.unlocked().program_at(LOCK_START + block, &[0])?;
+        Ok(())
+    }
+}
+
+/// Checks that `data` can be programmed at `offset` in a block with the `current` contents
+fn check_program(current: &[u8], locked: bool, offset: usize, data: &[u8]) -> Result<(), OtpError> {
+    if offset
+        .checked_add(data.len())
+        .map_or(true, |end| end > current.len())
+    {
+        Err(OtpError::OutOfBounds)
+    } else if locked {
+        Err(OtpError::Locked)
+    } else if current[offset..offset + data.len()]
+        .iter()
+        .any(|&b| b != 0xFF)
+    {
+        Err(OtpError::AlreadyProgrammed)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_checks() {
+        let mut block = [0xFF; 32];
+        assert_eq!(check_program(&block, false, 0, &[0; 32]), Ok(()));
+        assert_eq!(check_program(&block, false, 30, &[1, 2]), Ok(()));
+        assert_eq!(
+            check_program(&block, false, 31, &[1, 2]),
+            Err(OtpError::OutOfBounds)
+        );
+        assert_eq!(
+            check_program(&block, false, usize::MAX, &[1, 2]),
+            Err(OtpError::OutOfBounds)
+        );
+        assert_eq!(check_program(&block, true, 0, &[1]), Err(OtpError::Locked));
+
+        block[4] = 0xFE;
+        assert_eq!(check_program(&block, false, 0, &[0; 4]), Ok(()));
+        assert_eq!(
+            check_program(&block, false, 2, &[0; 4]),
+            Err(OtpError::AlreadyProgrammed)
+        );
+        assert_eq!(check_program(&block, false, 5, &[0; 27]), Ok(()));
+    }
+}
@@ -0,0 +1,513 @@
+//! Hash processor
+//!
+//! SHA-1 and MD5, plus SHA-224 and SHA-256 on the STM32F437/439/479, either as plain digests or
+//! as HMAC. Messages are fed in any number of parts with [`Context::update`], the processor pads
+//! the last word itself.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{
+//!     hash::{Algorithm, HashExt},
+//!     stm32,
+//! };
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let mut hash = dp.HASH.constrain();
+//!
+//! let mut context = hash.start(Algorithm::Sha1);
+//! context.update(b"hello ");
+//! context.update(b"world");
+//! let digest = context.finalize();
+//!
+//! let mut context = hash.start_hmac(Algorithm::Md5, b"secret key");
+//! context.update(b"hello world");
+//! let mac = context.finalize();
+//! ```
+//!
+//! With the `digest` feature, [`Sha1`], [`Md5`], `Sha224` and `Sha256` implement the RustCrypto
+//! [`digest`](https://docs.rs/digest/0.9) traits.
+
+use core::ops::Deref;
+
+use crate::dma::{
+    buffer::DmaWords,
+    config::DmaConfig,
+    traits::{Channel, DMASet, Stream},
+    MemoryToPeripheral, Transfer,
+};
+use crate::rcc::{Enable, Reset};
+use crate::stm32::HASH;
+
+/// Hash algorithm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Md5,
+    #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+    Sha224,
+    #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+    Sha256,
+}
+
+impl Algorithm {
+    /// Returns the length of the digest in bytes
+    pub fn digest_len(self) -> usize {
+        match self {
+            Algorithm::Sha1 => 20,
+            Algorithm::Md5 => 16,
+            #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+            Algorithm::Sha224 => 28,
+            #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+            Algorithm::Sha256 => 32,
+        }
+    }
+
+    /// ALGO1 and ALGO0
+    fn bits(self) -> (bool, bool) {
+        match self {
+            Algorithm::Sha1 => (false, false),
+            Algorithm::Md5 => (false, true),
+            #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+            Algorithm::Sha224 => (true, false),
+            #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+            Algorithm::Sha256 => (true, true),
+        }
+    }
+}
+
+/// Message digest or HMAC
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Digest {
+    bytes: [u8; 32],
+    len: usize,
+}
+
+impl Digest {
+    /// Builds the digest from the hash registers, each holding 4 bytes most significant first
+    fn from_words(words: impl Iterator<Item = u32>, len: usize) -> Self {
+        let mut bytes = [0; 32];
+        for (chunk, word) in bytes[..len].chunks_exact_mut(4).zip(words) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        Digest { bytes, len }
+    }
+}
+
+impl Deref for Digest {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Extension trait that constrains the `HASH` peripheral
+pub trait HashExt {
+    /// Enables and resets the hash processor
+    fn constrain(self) -> Hash;
+}
+
+impl HashExt for HASH {
+    fn constrain(self) -> Hash {
+        HASH::enable();
+        HASH::reset();
+        Hash { hash: self }
+    }
+}
+
+/// Hash processor
+pub struct Hash {
+    hash: HASH,
+}
+
+impl Hash {
+    /// Starts computing the digest of a message
+    pub fn start(&mut self, algorithm: Algorithm) -> Context<'_> {
+        let mut context = Context {
+            hash: self,
+            algorithm,
+            key: None,
+            tail: [0; 4],
+            tail_len: 0,
+        };
+        context.restart();
+        context
+    }
+
+    /// Starts computing the HMAC of a message with `key`
+    ///
+    /// Keys longer than a block, 64 bytes, are hashed first as HMAC requires.
+    pub fn start_hmac<'a>(&'a mut self, algorithm: Algorithm, key: &'a [u8]) -> Context<'a> {
+        let mut context = Context {
+            hash: self,
+            algorithm,
+            key: Some(key),
+            tail: [0; 4],
+            tail_len: 0,
+        };
+        context.restart();
+        context
+    }
+
+    /// Computes the digest of `data` with a DMA transfer, the CPU is free until it completes
+    ///
+    /// The DMA stream feeds the whole words, the bytes left, if any, are fed by
+    /// [`HashTransfer::wait`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` is not aligned on 4 bytes, or is shorter than 4 bytes or longer than
+    /// 65535 words.
+    pub fn digest_dma<STREAM, CHANNEL>(
+        self,
+        algorithm: Algorithm,
+        stream: STREAM,
+        data: &'static [u8],
+    ) -> HashTransfer<STREAM, CHANNEL>
+    where
+        STREAM: Stream,
+        CHANNEL: Channel,
+        (STREAM, CHANNEL, HASH, MemoryToPeripheral): DMASet,
+    {
+        assert_eq!(data.as_ptr() as usize % 4, 0);
+        let words = data.len() / 4;
+        assert!(words > 0 && words <= usize::from(u16::MAX));
+
+        self.init(algorithm, None);
+        // MDMAT keeps the processor from starting the final calculation at the end of the
+        // transfer, so the bytes left can be fed afterwards
+        self.hash
+            .cr
+            .modify(|_, w| w.mdmat().set_bit().dmae().set_bit());
+
+        let source = DmaWords {
+            ptr: data.as_ptr() as *mut u32,
+            len: words,
+        };
+        let config = DmaConfig::default().memory_increment(true);
+        let mut transfer = Transfer::init(stream, self.hash, source, None, config);
+        transfer.start(|_| {});
+
+        HashTransfer {
+            transfer,
+            algorithm,
+            tail: &data[words * 4..],
+        }
+    }
+
+    /// Releases the HASH peripheral
+    pub fn release(self) -> HASH {
+        self.hash
+    }
+
+    /// Selects the algorithm and starts a new message, `hmac_long_key` is `Some` for HMAC and
+    /// tells if the key is longer than a block
+    fn init(&self, algorithm: Algorithm, hmac_long_key: Option<bool>) {
+        let (algo1, algo0) = algorithm.bits();
+        // The bytes of each word are swapped, so they are processed in memory order
+        self.hash.cr.write(|w| unsafe {
+            w.algo1()
+                .bit(algo1)
+                .algo0()
+                .bit(algo0)
+                .datatype()
+                .bits(0b10)
+                .mode()
+                .bit(hmac_long_key.is_some())
+                .lkey()
+                .bit(hmac_long_key == Some(true))
+                .init()
+                .set_bit()
+        });
+    }
+
+    fn write_word(&self, word: &[u8]) {
+        let word = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        self.hash.din.write(|w| unsafe { w.bits(word) });
+    }
+
+    /// Feeds a whole message and starts its final calculation
+    fn write_message(&self, data: &[u8]) {
+        let mut words = data.chunks_exact(4);
+        for word in &mut words {
+            self.write_word(word);
+        }
+        self.end_message(words.remainder());
+    }
+
+    /// Feeds the last bytes of a message, less than a word, and starts its final calculation
+    fn end_message(&self, tail: &[u8]) {
+        let (word, nblw) = last_word(tail);
+        self.hash.str.write(|w| unsafe { w.nblw().bits(nblw) });
+        if nblw != 0 {
+            self.write_word(&word);
+        }
+        self.hash
+            .str
+            .write(|w| unsafe { w.nblw().bits(nblw).dcal().set_bit() });
+    }
+
+    fn wait_busy(&self) {
+        while self.hash.sr.read().busy().bit_is_set() {}
+    }
+
+    fn read_digest(&self, algorithm: Algorithm) -> Digest {
+        while self.hash.sr.read().dcis().bit_is_clear() {}
+
+        // The SHA-2 digests go on past the 5 words of HR0 to HR4
+        let hr = self.hash.hr.iter().map(|hr| hr.read().bits());
+        let hash_hr = self.hash.hash_hr[5..].iter().map(|hr| hr.read().bits());
+        Digest::from_words(hr.chain(hash_hr), algorithm.digest_len())
+    }
+}
+
+/// Message being hashed, see [`Hash::start`] and [`Hash::start_hmac`]
+pub struct Context<'a> {
+    hash: &'a mut Hash,
+    algorithm: Algorithm,
+    /// The HMAC key, which is fed again after the message
+    key: Option<&'a [u8]>,
+    /// Bytes that don't fill a word yet
+    tail: [u8; 4],
+    tail_len: usize,
+}
+
+impl Context<'_> {
+    /// Feeds the next part of the message
+    pub fn update(&mut self, mut data: &[u8]) {
+        if self.tail_len != 0 {
+            let len = data.len().min(4 - self.tail_len);
+            self.tail[self.tail_len..self.tail_len + len].copy_from_slice(&data[..len]);
+            self.tail_len += len;
+            data = &data[len..];
+            if self.tail_len < 4 {
+                return;
+            }
+            self.hash.write_word(&self.tail);
+            self.tail_len = 0;
+        }
+
+        let mut words = data.chunks_exact(4);
+        for word in &mut words {
+            self.hash.write_word(word);
+        }
+        let rest = words.remainder();
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.tail_len = rest.len();
+    }
+
+    /// Returns the digest, or the HMAC, of the message
+    pub fn finalize(mut self) -> Digest {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> Digest {
+        self.hash.end_message(&self.tail[..self.tail_len]);
+        self.tail_len = 0;
+        if let Some(key) = self.key {
+            self.hash.wait_busy();
+            self.hash.write_message(key);
+        }
+        self.hash.read_digest(self.algorithm)
+    }
+
+    /// Starts the message over
+    fn restart(&mut self) {
+        self.tail_len = 0;
+        match self.key {
+            None => self.hash.init(self.algorithm, None),
+            Some(key) => {
+                self.hash.init(self.algorithm, Some(key.len() > 64));
+                self.hash.write_message(key);
+                self.hash.wait_busy();
+            }
+        }
+    }
+}
+
+/// A DMA transfer that feeds the hash processor, see [`Hash::digest_dma`]
+pub struct HashTransfer<STREAM, CHANNEL>
+where
+    STREAM: Stream,
+{
+    transfer: Transfer<STREAM, CHANNEL, HASH, MemoryToPeripheral, DmaWords>,
+    algorithm: Algorithm,
+    tail: &'static [u8],
+}
+
+impl<STREAM, CHANNEL> HashTransfer<STREAM, CHANNEL>
+where
+    STREAM: Stream,
+    CHANNEL: Channel,
+    (STREAM, CHANNEL, HASH, MemoryToPeripheral): DMASet,
+{
+    /// Returns true once all the words were fed
+    pub fn is_complete(&self) -> bool {
+        STREAM::get_transfer_complete_flag()
+    }
+
+    /// Waits for the transfer to complete, feeds the bytes left and returns the hash processor,
+    /// the stream and the digest
+    pub fn wait(self) -> (Hash, STREAM, Digest) {
+        while !self.is_complete() {}
+
+        let (stream, hash, _, _) = self.transfer.free();
+        let hash = Hash { hash };
+        hash.hash.cr.modify(|_, w| w.dmae().clear_bit());
+        hash.end_message(self.tail);
+        let digest = hash.read_digest(self.algorithm);
+
+        (hash, stream, digest)
+    }
+}
+
+/// Returns the last word of a message ending with `tail`, padded with zeros, and the number of
+/// valid bits in it, NBLW
+fn last_word(tail: &[u8]) -> ([u8; 4], u8) {
+    let mut word = [0; 4];
+    word[..tail.len()].copy_from_slice(tail);
+    (word, 8 * tail.len() as u8)
+}
+
+#[cfg(feature = "digest")]
+mod rust_crypto {
+    use digest::{
+        consts::{U16, U20, U64},
+        generic_array::GenericArray,
+        BlockInput, FixedOutputDirty, Reset, Update,
+    };
+
+    use super::{Algorithm, Context, Hash};
+
+    macro_rules! adapters {
+        ($($(#[$attr:meta])* $name:ident: $algorithm:ident, $size:ident;)+) => {
+            $(
+                $(#[$attr])*
+                pub struct $name<'a>(Context<'a>);
+
+                $(#[$attr])*
+                impl<'a> $name<'a> {
+                    /// Starts computing a digest with the hash processor
+                    pub fn new(hash: &'a mut Hash) -> Self {
+                        $name(hash.start(Algorithm::$algorithm))
+                    }
+                }
+
+                $(#[$attr])*
+                impl BlockInput for $name<'_> {
+                    type BlockSize = U64;
+                }
+
+                $(#[$attr])*
+                impl Update for $name<'_> {
+                    fn update(&mut self, data: impl AsRef<[u8]>) {
+                        self.0.update(data.as_ref());
+                    }
+                }
+
+                $(#[$attr])*
+                impl FixedOutputDirty for $name<'_> {
+                    type OutputSize = $size;
+
+                    fn finalize_into_dirty(&mut self, out: &mut GenericArray<u8, $size>) {
+                        out.copy_from_slice(&self.0.finish());
+                    }
+                }
+
+                $(#[$attr])*
+                impl Reset for $name<'_> {
+                    fn reset(&mut self) {
+                        self.0.restart();
+                    }
+                }
+            )+
+        };
+    }
+
+    #[cfg(any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479"))]
+    use digest::consts::{U28, U32};
+
+    adapters! {
+        /// SHA-1 computed by the hash processor
+        Sha1: Sha1, U20;
+        /// MD5 computed by the hash processor
+        Md5: Md5, U16;
+        /// SHA-224 computed by the hash processor
+        #[cfg(any(
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f479"
+        ))]
+        Sha224: Sha224, U28;
+        /// SHA-256 computed by the hash processor
+        #[cfg(any(
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f479"
+        ))]
+        Sha256: Sha256, U32;
+    }
+}
+
+#[cfg(feature = "digest")]
+pub use rust_crypto::{Md5, Sha1};
+#[cfg(all(
+    feature = "digest",
+    any(feature = "stm32f437", feature = "stm32f439", feature = "stm32f479")
+))]
+pub use rust_crypto::{Sha224, Sha256};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_word_padding() {
+        assert_eq!(last_word(&[]), ([0; 4], 0));
+        assert_eq!(last_word(b"a"), ([b'a', 0, 0, 0], 8));
+        assert_eq!(last_word(b"abc"), ([b'a', b'b', b'c', 0], 24));
+    }
+
+    #[test]
+    fn digest_bytes() {
+        // MD5 and SHA-1 of "abc", as read from the hash registers
+        let md5 = Digest::from_words(
+            [0x9001_5098, 0x3CD2_4FB0, 0xD696_3F7D, 0x28E1_7F72]
+                .iter()
+                .copied(),
+            16,
+        );
+        assert_eq!(
+            &*md5,
+            &[
+                0x90, 0x01, 0x50, 0x98, 0x3C, 0xD2, 0x4F, 0xB0, 0xD6, 0x96, 0x3F, 0x7D, 0x28, 0xE1,
+                0x7F, 0x72
+            ]
+        );
+
+        let sha1 = Digest::from_words(
+            [
+                0xA999_3E36,
+                0x4706_816A,
+                0xBA3E_2571,
+                0x7850_C26C,
+                0x9CD0_D89D,
+            ]
+            .iter()
+            .copied(),
+            20,
+        );
+        assert_eq!(
+            &*sha1,
+            &[
+                0xA9, 0x99, 0x3E, 0x36, 0x47, 0x06, 0x81, 0x6A, 0xBA, 0x3E, 0x25, 0x71, 0x78, 0x50,
+                0xC2, 0x6C, 0x9C, 0xD0, 0xD8, 0x9D
+            ]
+        );
+    }
+}
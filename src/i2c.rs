@@ -41,7 +41,7 @@ use crate::stm32::I2C3;
     feature = "stm32f469",
     feature = "stm32f479"
 ))]
-use crate::stm32::{I2C1, I2C2, RCC};
+use crate::stm32::{I2C1, I2C2};
 
 #[cfg(any(
     feature = "stm32f401",
@@ -187,13 +187,14 @@ use crate::gpio::gpioh::{PH4, PH5, PH7, PH8};
 use crate::gpio::AF9;
 use crate::gpio::{AlternateOD, AF4};
 
-use crate::rcc::Clocks;
+use crate::rcc::{BusClock, Clocks, Enable, Reset};
 use crate::time::{Hertz, KiloHertz, U32Ext};
 
 /// I2C abstraction
 pub struct I2c<I2C, PINS> {
     i2c: I2C,
     pins: PINS,
+    speed: KiloHertz,
 }
 
 #[cfg(any(feature = "stm32f413", feature = "stm32f423",))]
@@ -507,7 +508,7 @@ use crate::{
         gpiof::{PF14, PF15},
     },
     pac::fmpi2c,
-    pac::FMPI2C,
+    pac::{FMPI2C, RCC},
 };
 
 #[cfg(any(feature = "stm32f413", feature = "stm32f423",))]
@@ -576,18 +577,14 @@ impl<PINS> I2c<I2C1, PINS> {
     where
         PINS: Pins<I2C1>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for I2C1
-        rcc.apb1enr.modify(|_, w| w.i2c1en().set_bit());
+        I2C1::enable();
 
         // Reset I2C1
-        rcc.apb1rstr.modify(|_, w| w.i2c1rst().set_bit());
-        rcc.apb1rstr.modify(|_, w| w.i2c1rst().clear_bit());
+        I2C1::reset();
 
-        let i2c = I2c { i2c, pins };
-        i2c.i2c_init(speed, clocks.pclk1());
+        let i2c = I2c { i2c, pins, speed };
+        i2c.i2c_init(speed, I2C1::clock(&clocks));
         i2c
     }
 }
@@ -616,18 +613,14 @@ impl<PINS> I2c<I2C2, PINS> {
     where
         PINS: Pins<I2C2>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for I2C2
-        rcc.apb1enr.modify(|_, w| w.i2c2en().set_bit());
+        I2C2::enable();
 
         // Reset I2C2
-        rcc.apb1rstr.modify(|_, w| w.i2c2rst().set_bit());
-        rcc.apb1rstr.modify(|_, w| w.i2c2rst().clear_bit());
+        I2C2::reset();
 
-        let i2c = I2c { i2c, pins };
-        i2c.i2c_init(speed, clocks.pclk1());
+        let i2c = I2c { i2c, pins, speed };
+        i2c.i2c_init(speed, I2C2::clock(&clocks));
         i2c
     }
 }
@@ -655,18 +648,14 @@ impl<PINS> I2c<I2C3, PINS> {
     where
         PINS: Pins<I2C3>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for I2C3
-        rcc.apb1enr.modify(|_, w| w.i2c3en().set_bit());
+        I2C3::enable();
 
         // Reset I2C3
-        rcc.apb1rstr.modify(|_, w| w.i2c3rst().set_bit());
-        rcc.apb1rstr.modify(|_, w| w.i2c3rst().clear_bit());
+        I2C3::reset();
 
-        let i2c = I2c { i2c, pins };
-        i2c.i2c_init(speed, clocks.pclk1());
+        let i2c = I2c { i2c, pins, speed };
+        i2c.i2c_init(speed, I2C3::clock(&clocks));
         i2c
     }
 }
@@ -677,16 +666,14 @@ impl<PINS> FMPI2c<FMPI2C, PINS> {
     where
         PINS: Pins<FMPI2C>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for FMPI2C
-        rcc.apb1enr.modify(|_, w| w.i2c4en().set_bit());
+        FMPI2C::enable();
 
         // Reset FMPI2C
-        rcc.apb1rstr.modify(|_, w| w.i2c4rst().set_bit());
-        rcc.apb1rstr.modify(|_, w| w.i2c4rst().clear_bit());
+        FMPI2C::reset();
 
+        // NOTE(unsafe) This executes only during initialisation
+        let rcc = unsafe { &(*RCC::ptr()) };
         rcc.dckcfgr2.modify(|_, w| w.i2cfmp1sel().hsi());
 
         let i2c = FMPI2c { i2c, pins };
@@ -767,6 +754,17 @@ where
         self.i2c.cr1.modify(|_, w| w.pe().set_bit());
     }
 
+    /// Recomputes the bus timings after the clocks have been reconfigured with
+    /// [`CFGR::reconfigure`](../rcc/struct.CFGR.html#method.reconfigure).
+    ///
+    /// Must not be called while a transfer is ongoing.
+    pub fn reclock(&mut self, clocks: &Clocks)
+    where
+        I2C: BusClock,
+    {
+        self.i2c_init(self.speed, I2C::clock(clocks));
+    }
+
     pub fn release(self) -> (I2C, PINS) {
         (self.i2c, self.pins)
     }
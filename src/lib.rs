@@ -86,16 +86,40 @@ pub use crate::stm32::interrupt;
 #[cfg(feature = "device-selected")]
 pub mod adc;
 #[cfg(feature = "device-selected")]
+pub mod backup;
+#[cfg(feature = "device-selected")]
 pub mod bb;
+#[cfg(feature = "device-selected")]
+pub mod crc;
+#[cfg(any(
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f479",
+))]
+pub mod cryp;
 #[cfg(all(
     feature = "device-selected",
     not(any(feature = "stm32f411", feature = "stm32f412", feature = "stm32f401",))
 ))]
 pub mod dac;
 #[cfg(feature = "device-selected")]
+pub mod dbgmcu;
+#[cfg(feature = "device-selected")]
 pub mod delay;
 #[cfg(feature = "device-selected")]
+pub mod flash;
+#[cfg(feature = "device-selected")]
 pub mod gpio;
+#[cfg(any(
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f479",
+))]
+pub mod hash;
 #[cfg(feature = "device-selected")]
 pub mod i2c;
 #[cfg(all(
@@ -161,9 +185,13 @@ pub mod prelude;
 #[cfg(feature = "device-selected")]
 pub mod pwm;
 #[cfg(feature = "device-selected")]
+pub mod pwr;
+#[cfg(feature = "device-selected")]
 pub mod qei;
 #[cfg(feature = "device-selected")]
 pub mod rcc;
+#[cfg(feature = "device-selected")]
+pub mod rtc;
 #[cfg(all(
     feature = "sdio",
     not(any(feature = "stm32f410", feature = "stm32f446",))
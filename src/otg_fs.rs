@@ -9,6 +9,7 @@ use crate::gpio::{
     gpioa::{PA11, PA12},
     Alternate, AF10,
 };
+use crate::rcc::{Enable, Reset};
 use crate::time::Hertz;
 
 pub use synopsys_usb_otg::UsbBus;
@@ -55,16 +56,9 @@ unsafe impl UsbPeripheral for USB {
     const ENDPOINT_COUNT: usize = 6;
 
     fn enable() {
-        let rcc = unsafe { &*stm32::RCC::ptr() };
-
-        cortex_m::interrupt::free(|_| {
-            // Enable USB peripheral
-            rcc.ahb2enr.modify(|_, w| w.otgfsen().set_bit());
-
-            // Reset USB peripheral
-            rcc.ahb2rstr.modify(|_, w| w.otgfsrst().set_bit());
-            rcc.ahb2rstr.modify(|_, w| w.otgfsrst().clear_bit());
-        });
+        // Enable and reset USB peripheral
+        stm32::OTG_FS_GLOBAL::enable();
+        stm32::OTG_FS_GLOBAL::reset();
     }
 
     fn ahb_frequency_hz(&self) -> u32 {
@@ -12,6 +12,7 @@ use crate::gpio::{
     gpiob::{PB14, PB15},
     Alternate, AF12,
 };
+use crate::rcc::{Enable, Reset};
 use crate::time::Hertz;
 
 pub use synopsys_usb_otg::UsbBus;
@@ -49,16 +50,9 @@ unsafe impl UsbPeripheral for USB {
     const ENDPOINT_COUNT: usize = 9;
 
     fn enable() {
-        let rcc = unsafe { &*stm32::RCC::ptr() };
-
-        cortex_m::interrupt::free(|_| {
-            // Enable USB peripheral
-            rcc.ahb1enr.modify(|_, w| w.otghsen().set_bit());
-
-            // Reset USB peripheral
-            rcc.ahb1rstr.modify(|_, w| w.otghsrst().set_bit());
-            rcc.ahb1rstr.modify(|_, w| w.otghsrst().clear_bit());
-        });
+        // Enable and reset USB peripheral
+        stm32::OTG_HS_GLOBAL::enable();
+        stm32::OTG_HS_GLOBAL::reset();
     }
 
     fn ahb_frequency_hz(&self) -> u32 {
@@ -21,7 +21,11 @@ use core::{marker::PhantomData, mem::MaybeUninit};
     feature = "stm32f479"
 ))]
 use crate::stm32::{TIM1, TIM11, TIM5, TIM9};
-use crate::{bb, hal, rcc::Clocks, stm32::RCC, time::Hertz};
+use crate::{
+    bb, hal,
+    rcc::{BusTimerClock, Clocks, Enable, Reset},
+    time::Hertz,
+};
 
 #[cfg(any(
     feature = "stm32f401",
@@ -154,23 +158,16 @@ macro_rules! brk {
 }
 
 macro_rules! pwm_all_channels {
-    ($($TIMX:ident: ($timX:ident, $apbenr:ident, $apbrstr:ident, $bit:expr, $pclk:ident, $ppre:ident),)+) => {
+    ($($TIMX:ident: $timX:ident,)+) => {
         $(
             pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, clocks: Clocks, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                {
-                    unsafe {
-                        //NOTE(unsafe) this reference will only be used for atomic writes with no side effects
-                        let rcc = &(*RCC::ptr());
-                        // Enable and reset the timer peripheral, it's the same bit position for both registers
-                        bb::set(&rcc.$apbenr, $bit);
-                        bb::set(&rcc.$apbrstr, $bit);
-                        bb::clear(&rcc.$apbrstr, $bit);
-                    }
-                }
+                // Enable and reset the timer peripheral
+                $TIMX::enable();
+                $TIMX::reset();
                 if PINS::C1 {
                     tim.ccmr1_output()
                         .modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode1() );
@@ -193,7 +190,7 @@ macro_rules! pwm_all_channels {
                 // might as well enable for the auto-reload too
                 tim.cr1.modify(|_, w| w.arpe().set_bit());
 
-                let clk = clocks.$pclk().0 * if clocks.$ppre() == 1 { 1 } else { 2 };
+                let clk = $TIMX::timer_clock(&clocks).0;
                 let ticks = clk / freq.into().0;
                 let psc = u16((ticks - 1) / (1 << 16)).unwrap();
                 tim.psc.write(|w| w.psc().bits(psc) );
@@ -340,23 +337,16 @@ macro_rules! pwm_all_channels {
 }
 
 macro_rules! pwm_2_channels {
-    ($($TIMX:ident: ($timX:ident, $apbenr:ident, $apbrstr:ident, $bit:expr, $pclk:ident, $ppre:ident),)+) => {
+    ($($TIMX:ident: $timX:ident,)+) => {
         $(
             pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, clocks: Clocks, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                {
-                    unsafe {
-                        //NOTE(unsafe) this reference will only be used for atomic writes with no side effects
-                        let rcc = &(*RCC::ptr());
-                        // Enable and reset the timer peripheral, it's the same bit position for both registers
-                        bb::set(&rcc.$apbenr, $bit);
-                        bb::set(&rcc.$apbrstr, $bit);
-                        bb::clear(&rcc.$apbrstr, $bit);
-                    }
-                }
+                // Enable and reset the timer peripheral
+                $TIMX::enable();
+                $TIMX::reset();
                 if PINS::C1 {
                     //NOTE(unsafe) 6 is a valid value to write to oc1m
                     unsafe {
@@ -375,7 +365,7 @@ macro_rules! pwm_2_channels {
                 // might as well enable for the auto-reload too
                 tim.cr1.modify(|_, w| w.arpe().set_bit());
 
-                let clk = clocks.$pclk().0 * if clocks.$ppre() == 1 { 1 } else { 2 };
+                let clk = $TIMX::timer_clock(&clocks).0;
                 let ticks = clk / freq.into().0;
                 let psc = u16((ticks - 1) / (1 << 16)).unwrap();
                 tim.psc.write(|w| w.psc().bits(psc) );
@@ -459,23 +449,16 @@ macro_rules! pwm_2_channels {
 }
 
 macro_rules! pwm_1_channel {
-    ($($TIMX:ident: ($timX:ident, $apbenr:ident, $apbrstr:ident, $bit:expr, $pclk:ident, $ppre:ident),)+) => {
+    ($($TIMX:ident: $timX:ident,)+) => {
         $(
             pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, clocks: Clocks, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                {
-                    unsafe {
-                        //NOTE(unsafe) this reference will only be used for atomic writes with no side effects
-                        let rcc = &(*RCC::ptr());
-                        // Enable and reset the timer peripheral, it's the same bit position for both registers
-                        bb::set(&rcc.$apbenr, $bit);
-                        bb::set(&rcc.$apbrstr, $bit);
-                        bb::clear(&rcc.$apbrstr, $bit);
-                    }
-                }
+                // Enable and reset the timer peripheral
+                $TIMX::enable();
+                $TIMX::reset();
                 if PINS::C1 {
                     //NOTE(unsafe) 6 is a valid value to write to oc1m
                     unsafe {
@@ -489,7 +472,7 @@ macro_rules! pwm_1_channel {
                 // might as well enable for the auto-reload too
                 tim.cr1.modify(|_, w| w.arpe().set_bit());
 
-                let clk = clocks.$pclk().0 * if clocks.$ppre() == 1 { 1 } else { 2 };
+                let clk = $TIMX::timer_clock(&clocks).0;
                 let ticks = clk / freq.into().0;
                 let psc = u16((ticks - 1) / (1 << 16)).unwrap();
                 tim.psc.write(|w| w.psc().bits(psc) );
@@ -543,23 +526,16 @@ macro_rules! pwm_1_channel {
 
 #[cfg(feature = "stm32f410")]
 macro_rules! pwm_tim5_f410 {
-    ($($TIMX:ident: ($timX:ident, $apbenr:ident, $apbrstr:ident, $bit:expr, $pclk:ident, $ppre:ident),)+) => {
+    ($($TIMX:ident: $timX:ident,)+) => {
         $(
             pub fn $timX<P, PINS, T>(tim: $TIMX, _pins: PINS, clocks: Clocks, freq: T) -> PINS::Channels
             where
                 PINS: Pins<$TIMX, P>,
                 T: Into<Hertz>,
             {
-                {
-                    unsafe {
-                        //NOTE(unsafe) this reference will only be used for atomic writes with no side effects
-                        let rcc = &(*RCC::ptr());
-                        // Enable and reset the timer peripheral, it's the same bit position for both registers
-                        bb::set(&rcc.$apbenr, $bit);
-                        bb::set(&rcc.$apbrstr, $bit);
-                        bb::clear(&rcc.$apbrstr, $bit);
-                    }
-                }
+                // Enable and reset the timer peripheral
+                $TIMX::enable();
+                $TIMX::reset();
                 if PINS::C1 {
                     tim.ccmr1_output()
                         .modify(|_, w| w.oc1pe().set_bit().oc1m().pwm_mode1() );
@@ -582,7 +558,7 @@ macro_rules! pwm_tim5_f410 {
                 // might as well enable for the auto-reload too
                 tim.cr1.modify(|_, w| w.arpe().set_bit());
 
-                let clk = clocks.$pclk().0 * if clocks.$ppre() == 1 { 1 } else { 2 };
+                let clk = $TIMX::timer_clock(&clocks).0;
                 let ticks = clk / freq.into().0;
                 let psc = u16((ticks - 1) / (1 << 16)).unwrap();
                 tim.psc.write(|w| w.psc().bits(psc) );
@@ -747,7 +723,7 @@ macro_rules! pwm_tim5_f410 {
     feature = "stm32f479"
 ))]
 pwm_all_channels!(
-    TIM1: (tim1, apb2enr, apb2rstr, 0u8, pclk2, ppre2),
+    TIM1: tim1,
 );
 
 #[cfg(any(
@@ -770,7 +746,7 @@ pwm_all_channels!(
     feature = "stm32f479"
 ))]
 pwm_2_channels!(
-    TIM9: (tim9, apb2enr, apb2rstr, 16u8, pclk2, ppre2),
+    TIM9: tim9,
 );
 
 #[cfg(any(
@@ -793,7 +769,7 @@ pwm_2_channels!(
     feature = "stm32f479"
 ))]
 pwm_1_channel!(
-    TIM11: (tim11, apb2enr, apb2rstr, 18u8, pclk2, ppre2),
+    TIM11: tim11,
 );
 
 #[cfg(any(
@@ -815,10 +791,10 @@ pwm_1_channel!(
     feature = "stm32f479"
 ))]
 pwm_all_channels!(
-    TIM2: (tim2, apb1enr, apb1rstr, 0u8, pclk1, ppre1),
-    TIM3: (tim3, apb1enr, apb1rstr, 1u8, pclk1, ppre1),
-    TIM4: (tim4, apb1enr, apb1rstr, 2u8, pclk1, ppre1),
-    TIM5: (tim5, apb1enr, apb1rstr, 3u8, pclk1, ppre1),
+    TIM2: tim2,
+    TIM3: tim3,
+    TIM4: tim4,
+    TIM5: tim5,
 );
 
 #[cfg(any(
@@ -840,7 +816,7 @@ pwm_all_channels!(
     feature = "stm32f479"
 ))]
 pwm_1_channel!(
-    TIM10: (tim10, apb2enr, apb2rstr, 17u8, pclk2, ppre2),
+    TIM10: tim10,
 );
 
 #[cfg(any(
@@ -860,7 +836,7 @@ pwm_1_channel!(
     feature = "stm32f479"
 ))]
 pwm_all_channels!(
-    TIM8: (tim8, apb2enr, apb2rstr, 1u8, pclk2, ppre2),
+    TIM8: tim8,
 );
 
 #[cfg(any(
@@ -880,7 +856,7 @@ pwm_all_channels!(
     feature = "stm32f479"
 ))]
 pwm_2_channels!(
-    TIM12: (tim12, apb1enr, apb1rstr, 6u8, pclk1, ppre1),
+    TIM12: tim12,
 );
 
 #[cfg(any(
@@ -900,11 +876,11 @@ pwm_2_channels!(
     feature = "stm32f479"
 ))]
 pwm_1_channel!(
-    TIM13: (tim13, apb1enr, apb1rstr, 7u8, pclk1, ppre1),
-    TIM14: (tim14, apb1enr, apb1rstr, 8u8, pclk1, ppre1),
+    TIM13: tim13,
+    TIM14: tim14,
 );
 
 #[cfg(feature = "stm32f410")]
 pwm_tim5_f410!(
-    TIM5: (tim5, apb1enr, apb1rstr, 3u8, pclk1, ppre1),
+    TIM5: tim5,
 );
@@ -0,0 +1,227 @@
+//! Power control and low-power modes
+//!
+//! - Sleep stops the core clock, any interrupt wakes it up.
+//! - Stop also stops all clocks of the 1.2 V domain and the oscillators, SRAM and registers are
+//!   kept. Any EXTI line wakes it up, the clocks that were running are restarted afterwards.
+//! - Standby powers down the 1.2 V domain, only the backup domain is kept. The device restarts
+//!   from reset on a rising edge of a wakeup pin, an RTC event, an IWDG reset or the NRST pin.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{
+//!     prelude::*,
+//!     pwr::{PwrExt, StopConfig},
+//!     stm32,
+//! };
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let mut cp = cortex_m::Peripherals::take().unwrap();
+//! let _clocks = dp.RCC.constrain().cfgr.use_hse(8.mhz()).sysclk(84.mhz()).freeze();
+//! let mut pwr = dp.PWR.constrain();
+//!
+//! // Wait for an EXTI interrupt, then continue at 84 MHz
+//! pwr.stop(
+//!     &mut cp.SCB,
+//!     StopConfig::default()
+//!         .low_power_regulator(true)
+//!         .flash_power_down(true),
+//! );
+//! ```
+
+use cortex_m::asm;
+use cortex_m::peripheral::SCB;
+
+use crate::rcc::{Enable, StopState};
+use crate::stm32::PWR;
+
+/// Extension trait that constrains the `PWR` peripheral
+pub trait PwrExt {
+    /// Enables the `PWR` clock and constrains the peripheral
+    fn constrain(self) -> Pwr;
+}
+
+impl PwrExt for PWR {
+    fn constrain(self) -> Pwr {
+        PWR::enable();
+        Pwr { pwr: self }
+    }
+}
+
+/// Constrained PWR peripheral
+pub struct Pwr {
+    pwr: PWR,
+}
+
+/// Regulator and flash settings during Stop mode
+///
+/// Lower power settings take longer to wake up from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StopConfig {
+    /// Runs the low-power regulator instead of the main regulator
+    pub low_power_regulator: bool,
+    /// Powers the flash memory down
+    pub flash_power_down: bool,
+    /// Puts the regulator in under-drive mode, the flash is powered down as well
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    pub under_drive: bool,
+}
+
+impl StopConfig {
+    pub fn low_power_regulator(mut self, enable: bool) -> Self {
+        self.low_power_regulator = enable;
+        self
+    }
+
+    pub fn flash_power_down(mut self, enable: bool) -> Self {
+        self.flash_power_down = enable;
+        self
+    }
+
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    pub fn under_drive(mut self, enable: bool) -> Self {
+        self.under_drive = enable;
+        self
+    }
+}
+
+/// Pins that wake the device up from Standby on a rising edge
+///
+/// The pins are forced to input pull-down while enabled, whatever their GPIO configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WakeupPins {
+    /// WKUP1 on PA0
+    pub pa0: bool,
+    /// WKUP2 on PC0
+    #[cfg(feature = "stm32f410")]
+    pub pc0: bool,
+    /// WKUP3 on PC1
+    #[cfg(feature = "stm32f410")]
+    pub pc1: bool,
+    /// WKUP2 on PC13
+    #[cfg(feature = "stm32f446")]
+    pub pc13: bool,
+}
+
+impl Pwr {
+    /// Stops the core clock until an interrupt occurs
+    pub fn sleep(&mut self, scb: &mut SCB) {
+        scb.clear_sleepdeep();
+        asm::dsb();
+        asm::wfi();
+    }
+
+    /// Enters Stop mode until an EXTI line fires
+    ///
+    /// The oscillators, PLLs and system clock source that were running, as configured by
+    /// `rcc::CFGR::freeze`, are restarted before returning, so the `Clocks` stay valid.
+    pub fn stop(&mut self, scb: &mut SCB, config: StopConfig) {
+        let state = StopState::save();
+
+        self.pwr.cr.modify(|_, w| {
+            w.pdds().clear_bit();
+            w.lpds().bit(config.low_power_regulator);
+            w.fpds().bit(config.flash_power_down)
+        });
+
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f446",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        {
+            // The PAC names the under-drive bits of the main (MRUDS) and low-power (LPUDS)
+            // regulators after their low-voltage counterparts on other devices
+            let under_drive = config.under_drive;
+            self.pwr.cr.modify(|_, w| unsafe {
+                w.uden().bits(if under_drive { 0b11 } else { 0b00 });
+                w.mrlvds().bit(under_drive && !config.low_power_regulator);
+                w.lplvds().bit(under_drive && config.low_power_regulator)
+            });
+        }
+
+        scb.set_sleepdeep();
+        asm::dsb();
+        asm::wfi();
+        scb.clear_sleepdeep();
+
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f446",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        {
+            if config.under_drive {
+                self.pwr.cr.modify(|_, w| unsafe { w.uden().bits(0b00) });
+                // Cleared by writing ones
+                self.pwr.csr.modify(|_, w| unsafe { w.udrdy().bits(0b11) });
+            }
+        }
+
+        state.restore();
+    }
+
+    /// Enters Standby mode, the device restarts from reset when it wakes up
+    ///
+    /// Use [`woke_from_standby`](#method.woke_from_standby) after the restart to tell it apart
+    /// from other resets.
+    pub fn standby(&mut self, scb: &mut SCB, pins: WakeupPins) -> ! {
+        #[cfg(not(any(feature = "stm32f410", feature = "stm32f446")))]
+        self.pwr.csr.modify(|_, w| w.ewup().bit(pins.pa0));
+        #[cfg(feature = "stm32f410")]
+        self.pwr.csr.modify(|_, w| {
+            w.ewup1().bit(pins.pa0);
+            w.ewup2().bit(pins.pc0);
+            w.ewup3().bit(pins.pc1)
+        });
+        #[cfg(feature = "stm32f446")]
+        self.pwr
+            .csr
+            .modify(|_, w| w.ewup().bit(pins.pa0).ewup2().bit(pins.pc13));
+
+        // A wakeup flag left set makes the device leave Standby immediately
+        self.pwr
+            .cr
+            .modify(|_, w| w.pdds().set_bit().cwuf().set_bit());
+
+        scb.set_sleepdeep();
+        asm::dsb();
+        loop {
+            asm::wfi();
+        }
+    }
+
+    /// Allows writes to the backup domain: the RTC, the backup registers and the RCC_BDCR register
+    pub fn enable_backup_access(&mut self) {
+        self.pwr.cr.modify(|_, w| w.dbp().set_bit());
+    }
+
+    /// Returns `true` if the device was restarted by a wakeup from Standby, and clears the flag
+    pub fn woke_from_standby(&mut self) -> bool {
+        let standby = self.pwr.csr.read().sbf().bit_is_set();
+        self.pwr.cr.modify(|_, w| w.csbf().set_bit());
+        standby
+    }
+}
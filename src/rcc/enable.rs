@@ -0,0 +1,863 @@
+//! Peripheral clock enable, reset and bus clock frequency
+//!
+//! Every PAC peripheral implements the traits of this module, so drivers can enable and reset
+//! their peripheral without knowing which bus it is on, and user code can gate the clocks of
+//! peripherals the HAL does not wrap.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{rcc::Enable, stm32};
+//!
+//! stm32::CRC::enable();
+//! ```
+//!
+//! The registers are only ever modified through bit-banding, which is atomic, so these functions
+//! can be called from any context.
+
+use super::Clocks;
+use crate::bb;
+use crate::stm32::RCC;
+use crate::time::Hertz;
+
+mod sealed {
+    pub trait Sealed {}
+}
+use sealed::Sealed;
+
+/// Bus a peripheral is attached to
+pub trait RccBus: Sealed {
+    /// The bus type, one of `AHB1`, `AHB2`, `AHB3`, `APB1` or `APB2`
+    type Bus;
+}
+
+/// Enables and disables the clock of a peripheral
+pub trait Enable: RccBus {
+    /// Enables the clock of the peripheral
+    fn enable();
+
+    /// Disables the clock of the peripheral
+    fn disable();
+
+    /// Returns true if the clock of the peripheral is enabled
+    fn is_enabled() -> bool;
+}
+
+/// Enables and disables the clock of a peripheral in sleep mode
+pub trait LPEnable: RccBus {
+    /// Keeps the peripheral clocked in sleep mode, this is the reset state
+    fn low_power_enable();
+
+    /// Stops the clock of the peripheral in sleep mode
+    fn low_power_disable();
+}
+
+/// Resets a peripheral
+pub trait Reset: RccBus {
+    /// Resets all the registers of the peripheral
+    fn reset();
+}
+
+/// Frequency of the clock a peripheral runs from
+pub trait BusClock {
+    /// Returns the bus clock frequency for the given clock configuration
+    fn clock(clocks: &Clocks) -> Hertz;
+}
+
+/// Frequency of the clock the timers of a bus run from
+pub trait BusTimerClock {
+    /// Returns the timer clock frequency for the given clock configuration, which is twice the
+    /// bus clock if the APB prescaler is not 1
+    fn timer_clock(clocks: &Clocks) -> Hertz;
+}
+
+impl<T> BusClock for T
+where
+    T: RccBus,
+    T::Bus: BusClock,
+{
+    fn clock(clocks: &Clocks) -> Hertz {
+        T::Bus::clock(clocks)
+    }
+}
+
+impl<T> BusTimerClock for T
+where
+    T: RccBus,
+    T::Bus: BusTimerClock,
+{
+    fn timer_clock(clocks: &Clocks) -> Hertz {
+        T::Bus::timer_clock(clocks)
+    }
+}
+
+/// Advanced High-performance Bus 1
+pub struct AHB1 {
+    _0: (),
+}
+
+/// Advanced High-performance Bus 2
+pub struct AHB2 {
+    _0: (),
+}
+
+/// Advanced High-performance Bus 3
+pub struct AHB3 {
+    _0: (),
+}
+
+/// Advanced Peripheral Bus 1
+pub struct APB1 {
+    _0: (),
+}
+
+/// Advanced Peripheral Bus 2
+pub struct APB2 {
+    _0: (),
+}
+
+impl BusClock for AHB1 {
+    fn clock(clocks: &Clocks) -> Hertz {
+        clocks.hclk
+    }
+}
+
+impl BusClock for AHB2 {
+    fn clock(clocks: &Clocks) -> Hertz {
+        clocks.hclk
+    }
+}
+
+impl BusClock for AHB3 {
+    fn clock(clocks: &Clocks) -> Hertz {
+        clocks.hclk
+    }
+}
+
+impl BusClock for APB1 {
+    fn clock(clocks: &Clocks) -> Hertz {
+        clocks.pclk1
+    }
+}
+
+impl BusClock for APB2 {
+    fn clock(clocks: &Clocks) -> Hertz {
+        clocks.pclk2
+    }
+}
+
+impl BusTimerClock for APB1 {
+    fn timer_clock(clocks: &Clocks) -> Hertz {
+        let mul = if clocks.ppre1 == 1 { 1 } else { 2 };
+        Hertz(clocks.pclk1.0 * mul)
+    }
+}
+
+impl BusTimerClock for APB2 {
+    fn timer_clock(clocks: &Clocks) -> Hertz {
+        let mul = if clocks.ppre2 == 1 { 1 } else { 2 };
+        Hertz(clocks.pclk2.0 * mul)
+    }
+}
+
+macro_rules! bus_enable {
+    ($PER:ident => ($busX:ident, $enr:ident, $bit:literal)) => {
+        bus_enable!(@ty crate::stm32::$PER => ($busX, $enr, $bit));
+    };
+    (@ty $PER:ty => ($busX:ident, $enr:ident, $bit:literal)) => {
+        impl Sealed for $PER {}
+
+        impl RccBus for $PER {
+            type Bus = $busX;
+        }
+
+        impl Enable for $PER {
+            #[inline(always)]
+            fn enable() {
+                unsafe {
+                    bb::set(&(*RCC::ptr()).$enr, $bit);
+                    // The peripheral can only be accessed two clock cycles after it is enabled,
+                    // reading back the register takes care of that
+                    let _ = (*RCC::ptr()).$enr.read();
+                }
+            }
+
+            #[inline(always)]
+            fn disable() {
+                unsafe { bb::clear(&(*RCC::ptr()).$enr, $bit) }
+            }
+
+            #[inline(always)]
+            fn is_enabled() -> bool {
+                // NOTE(unsafe) atomic read with no side effects
+                unsafe { (*RCC::ptr()).$enr.read().bits() & (1 << $bit) != 0 }
+            }
+        }
+    };
+}
+
+macro_rules! bus_lpenable {
+    ($PER:ident => ($lpenr:ident, $bit:literal)) => {
+        bus_lpenable!(@ty crate::stm32::$PER => ($lpenr, $bit));
+    };
+    (@ty $PER:ty => ($lpenr:ident, $bit:literal)) => {
+        impl LPEnable for $PER {
+            #[inline(always)]
+            fn low_power_enable() {
+                unsafe { bb::set(&(*RCC::ptr()).$lpenr, $bit) }
+            }
+
+            #[inline(always)]
+            fn low_power_disable() {
+                unsafe { bb::clear(&(*RCC::ptr()).$lpenr, $bit) }
+            }
+        }
+    };
+}
+
+macro_rules! bus_reset {
+    ($PER:ident => ($rstr:ident, $bit:literal)) => {
+        impl Reset for crate::stm32::$PER {
+            #[inline(always)]
+            fn reset() {
+                unsafe {
+                    bb::set(&(*RCC::ptr()).$rstr, $bit);
+                    bb::clear(&(*RCC::ptr()).$rstr, $bit);
+                }
+            }
+        }
+    };
+}
+
+macro_rules! bus {
+    ($busX:ident: ($enr:ident, $lpenr:ident, $rstr:ident) { $($PER:ident: $bit:literal,)+ }) => {
+        $(
+            bus_enable!($PER => ($busX, $enr, $bit));
+            bus_lpenable!($PER => ($lpenr, $bit));
+            bus_reset!($PER => ($rstr, $bit));
+        )+
+    };
+    ($busX:ident: ($enr:ident, $lpenr:ident) { $($PER:ident: $bit:literal,)+ }) => {
+        $(
+            bus_enable!($PER => ($busX, $enr, $bit));
+            bus_lpenable!($PER => ($lpenr, $bit));
+        )+
+    };
+}
+
+bus! {
+    AHB1: (ahb1enr, ahb1lpenr, ahb1rstr) {
+        GPIOA: 0,
+        GPIOB: 1,
+        GPIOC: 2,
+        GPIOH: 7,
+        CRC: 12,
+        DMA1: 21,
+        DMA2: 22,
+    }
+}
+
+bus! {
+    APB1: (apb1enr, apb1lpenr, apb1rstr) {
+        TIM5: 3,
+        WWDG: 11,
+        SPI2: 14,
+        USART2: 17,
+        I2C1: 21,
+        I2C2: 22,
+        PWR: 28,
+    }
+}
+
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        TIM1: 0,
+        USART1: 4,
+        USART6: 5,
+        SPI1: 12,
+        SYSCFG: 14,
+        TIM9: 16,
+        TIM11: 18,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB1: (ahb1enr, ahb1lpenr, ahb1rstr) {
+        GPIOD: 3,
+        GPIOE: 4,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB1: (apb1enr, apb1lpenr, apb1rstr) {
+        TIM2: 0,
+        TIM3: 1,
+        TIM4: 2,
+        SPI3: 15,
+        I2C3: 23,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        TIM10: 17,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        SDIO: 11,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f410",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB1: (apb1enr, apb1lpenr, apb1rstr) {
+        TIM6: 4,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        SPI4: 13,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f410",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB1: (apb1enr, apb1lpenr, apb1rstr) {
+        DAC: 29,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB1: (ahb1enr, ahb1lpenr, ahb1rstr) {
+        GPIOF: 5,
+        GPIOG: 6,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB1: (apb1enr, apb1lpenr, apb1rstr) {
+        TIM7: 5,
+        TIM12: 6,
+        TIM13: 7,
+        TIM14: 8,
+        USART3: 18,
+        CAN1: 25,
+        CAN2: 26,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        TIM8: 1,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB2: (ahb2enr, ahb2lpenr, ahb2rstr) {
+        RNG: 6,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB1: (apb1enr, apb1lpenr, apb1rstr) {
+        UART4: 19,
+        UART5: 20,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f410",
+    feature = "stm32f411",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        SPI5: 20,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB2: (ahb2enr, ahb2lpenr, ahb2rstr) {
+        DCMI: 0,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB2: (ahb2enr, ahb2lpenr, ahb2rstr) {
+        CRYP: 4,
+        HASH: 5,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB1: (ahb1enr, ahb1lpenr, ahb1rstr) {
+        GPIOI: 8,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB1: (apb1enr, apb1lpenr, apb1rstr) {
+        UART7: 30,
+        UART8: 31,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f413",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f423"
+))]
+bus! {
+    AHB3: (ahb3enr, ahb3lpenr, ahb3rstr) {
+        FSMC: 0,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f429",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        SAI: 22,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB3: (ahb3enr, ahb3lpenr, ahb3rstr) {
+        QUADSPI: 1,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB1: (ahb1enr, ahb1lpenr, ahb1rstr) {
+        GPIOJ: 9,
+        GPIOK: 10,
+        DMA2D: 23,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        SPI6: 21,
+        LTDC: 26,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f429",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB3: (ahb3enr, ahb3lpenr, ahb3rstr) {
+        FMC: 0,
+    }
+}
+
+#[cfg(any(feature = "stm32f427", feature = "stm32f437", feature = "stm32f446"))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        SAI1: 22,
+    }
+}
+
+#[cfg(feature = "stm32f410")]
+bus! {
+    AHB1: (ahb1enr, ahb1lpenr, ahb1rstr) {
+        RNG: 31,
+    }
+}
+
+// The STM32F412 has no low power enable bits on the AHB3
+#[cfg(feature = "stm32f412")]
+bus_enable!(FSMC => (AHB3, ahb3enr, 0));
+#[cfg(feature = "stm32f412")]
+bus_reset!(FSMC => (ahb3rstr, 0));
+#[cfg(feature = "stm32f412")]
+bus_enable!(QUADSPI => (AHB3, ahb3enr, 1));
+#[cfg(feature = "stm32f412")]
+bus_reset!(QUADSPI => (ahb3rstr, 1));
+
+#[cfg(any(feature = "stm32f413", feature = "stm32f423"))]
+bus! {
+    APB1: (apb1enr, apb1lpenr, apb1rstr) {
+        FMPI2C: 24,
+        CAN3: 27,
+    }
+}
+
+#[cfg(any(feature = "stm32f413", feature = "stm32f423"))]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        UART9: 6,
+        UART10: 7,
+    }
+}
+
+#[cfg(feature = "stm32f446")]
+bus! {
+    APB2: (apb2enr, apb2lpenr, apb2rstr) {
+        SAI2: 23,
+    }
+}
+
+// ADCRST resets all the ADCs at once, so they have no `Reset` implementation
+bus! {
+    APB2: (apb2enr, apb2lpenr) {
+        ADC1: 8,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    APB2: (apb2enr, apb2lpenr) {
+        ADC2: 9,
+        ADC3: 10,
+    }
+}
+
+#[cfg(not(feature = "stm32f410"))]
+bus! {
+    AHB2: (ahb2enr, ahb2lpenr, ahb2rstr) {
+        OTG_FS_GLOBAL: 7,
+    }
+}
+
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus! {
+    AHB1: (ahb1enr, ahb1lpenr, ahb1rstr) {
+        OTG_HS_GLOBAL: 29,
+    }
+}
+
+// The backup SRAM is not a PAC peripheral, and has no reset bit
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus_enable!(@ty crate::backup::BackupSram => (AHB1, ahb1enr, 18));
+#[cfg(any(
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f446",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+bus_lpenable!(@ty crate::backup::BackupSram => (ahb1lpenr, 18));
@@ -0,0 +1,216 @@
+//! Microcontroller clock outputs
+//!
+//! An internal clock can be routed to PA8 (MCO1) or PC9 (MCO2), divided by a prescaler of 1 to 5.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{
+//!     gpio::Speed,
+//!     prelude::*,
+//!     rcc::{Mco1, Mco1Source, McoPre},
+//!     stm32,
+//! };
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let clocks = dp.RCC.constrain().cfgr.use_hse(8.mhz()).freeze();
+//!
+//! let gpioa = dp.GPIOA.split();
+//! let mco1 = Mco1::new(
+//!     gpioa.pa8.into_alternate_af0(),
+//!     Mco1Source::Hse,
+//!     McoPre::Div1,
+//!     Speed::Medium,
+//!     &clocks,
+//! )
+//! .unwrap();
+//! assert_eq!(mco1.freq().0, 8_000_000);
+//! ```
+
+use crate::gpio::gpioa::PA8;
+use crate::gpio::gpioc::PC9;
+use crate::gpio::{Alternate, Speed, AF0};
+use crate::stm32::RCC;
+use crate::time::Hertz;
+
+use super::{ClockError, Clocks};
+
+const LSE: u32 = 32_768; // Hz
+
+/// Clock source of MCO1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mco1Source {
+    /// High speed internal oscillator
+    Hsi = 0b00,
+    /// Low speed external oscillator, assumed to run at 32.768 kHz
+    Lse = 0b01,
+    /// High speed external oscillator
+    Hse = 0b10,
+    /// Main PLL P output
+    Pll = 0b11,
+}
+
+/// Clock source of MCO2
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mco2Source {
+    /// System clock
+    Sysclk = 0b00,
+    /// PLLI2S R output
+    #[cfg(not(feature = "stm32f410"))]
+    PllI2s = 0b01,
+    /// High speed external oscillator
+    Hse = 0b10,
+    /// Main PLL P output
+    Pll = 0b11,
+}
+
+/// Division factor of a clock output
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum McoPre {
+    Div1 = 1,
+    Div2 = 2,
+    Div3 = 3,
+    Div4 = 4,
+    Div5 = 5,
+}
+
+impl McoPre {
+    fn bits(self) -> u8 {
+        match self {
+            McoPre::Div1 => 0b000,
+            _ => 0b100 | (self as u8 - 2),
+        }
+    }
+}
+
+/// Clock output on PA8
+pub struct Mco1 {
+    pin: PA8<Alternate<AF0>>,
+    freq: Hertz,
+}
+
+impl Mco1 {
+    /// Routes `source` divided by `pre` to PA8, driving the pin at `speed`.
+    ///
+    /// Returns an error if the source is not running or the resulting frequency is too high for
+    /// the pin speed.
+    pub fn new(
+        pin: PA8<Alternate<AF0>>,
+        source: Mco1Source,
+        pre: McoPre,
+        speed: Speed,
+        clocks: &Clocks,
+    ) -> Result<Self, ClockError> {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        let source_freq = match source {
+            Mco1Source::Hsi => Some(Hertz(super::HSI)),
+            Mco1Source::Lse => {
+                if rcc.bdcr.read().lserdy().bit_is_set() {
+                    Some(Hertz(LSE))
+                } else {
+                    None
+                }
+            }
+            Mco1Source::Hse => clocks.hse,
+            Mco1Source::Pll => clocks.pllclk,
+        };
+        let freq = output_freq(source_freq, pre, &speed)?;
+
+        rcc.cfgr.modify(|_, w| unsafe {
+            w.mco1().bits(source as u8);
+            w.mco1pre().bits(pre.bits())
+        });
+
+        Ok(Mco1 {
+            pin: pin.set_speed(speed),
+            freq,
+        })
+    }
+
+    /// Returns the output frequency
+    pub fn freq(&self) -> Hertz {
+        self.freq
+    }
+
+    /// Releases the pin, the clock keeps being output until the pin mode is changed
+    pub fn release(self) -> PA8<Alternate<AF0>> {
+        self.pin
+    }
+}
+
+/// Clock output on PC9
+pub struct Mco2 {
+    pin: PC9<Alternate<AF0>>,
+    freq: Hertz,
+}
+
+impl Mco2 {
+    /// Routes `source` divided by `pre` to PC9, driving the pin at `speed`.
+    ///
+    /// Returns an error if the source is not running or the resulting frequency is too high for
+    /// the pin speed.
+    pub fn new(
+        pin: PC9<Alternate<AF0>>,
+        source: Mco2Source,
+        pre: McoPre,
+        speed: Speed,
+        clocks: &Clocks,
+    ) -> Result<Self, ClockError> {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        let source_freq = match source {
+            Mco2Source::Sysclk => Some(clocks.sysclk),
+            #[cfg(not(feature = "stm32f410"))]
+            Mco2Source::PllI2s => clocks.plli2s_clk,
+            Mco2Source::Hse => clocks.hse,
+            Mco2Source::Pll => clocks.pllclk,
+        };
+        let freq = output_freq(source_freq, pre, &speed)?;
+
+        rcc.cfgr.modify(|_, w| unsafe {
+            w.mco2().bits(source as u8);
+            w.mco2pre().bits(pre.bits())
+        });
+
+        Ok(Mco2 {
+            pin: pin.set_speed(speed),
+            freq,
+        })
+    }
+
+    /// Returns the output frequency
+    pub fn freq(&self) -> Hertz {
+        self.freq
+    }
+
+    /// Releases the pin, the clock keeps being output until the pin mode is changed
+    pub fn release(self) -> PC9<Alternate<AF0>> {
+        self.pin
+    }
+}
+
+/// Highest frequency a pin can toggle at for a given speed, with VDD above 2.7 V and the load
+/// capacitance given in the datasheet
+fn max_freq(speed: &Speed) -> u32 {
+    match speed {
+        Speed::Low => 2_000_000,
+        Speed::Medium => 25_000_000,
+        Speed::High => 50_000_000,
+        Speed::VeryHigh => 100_000_000,
+    }
+}
+
+fn output_freq(
+    source_freq: Option<Hertz>,
+    pre: McoPre,
+    speed: &Speed,
+) -> Result<Hertz, ClockError> {
+    let freq = source_freq.ok_or(ClockError::McoSourceDisabled)?.0 / pre as u32;
+    let max = max_freq(speed);
+    if freq > max {
+        return Err(ClockError::McoTooFast {
+            achieved: Hertz(freq),
+            max: Hertz(max),
+        });
+    }
+    Ok(Hertz(freq))
+}
@@ -0,0 +1,2257 @@
+use crate::stm32::rcc::cfgr::{HPRE_A, SW_A};
+use crate::stm32::{PWR, RCC};
+
+use crate::time::Hertz;
+
+mod enable;
+mod mco;
+pub mod pll;
+mod reset_reason;
+pub use enable::{
+    BusClock, BusTimerClock, Enable, LPEnable, RccBus, Reset, AHB1, AHB2, AHB3, APB1, APB2,
+};
+pub use mco::{Mco1, Mco1Source, Mco2, Mco2Source, McoPre};
+#[cfg(not(feature = "stm32f410"))]
+use pll::I2sPll;
+use pll::MainPll;
+use pll::SaiPll;
+pub use reset_reason::ResetReason;
+
+/// Extension trait that constrains the `RCC` peripheral
+pub trait RccExt {
+    /// Constrains the `RCC` peripheral so it plays nicely with the other abstractions
+    fn constrain(self) -> Rcc;
+}
+
+impl RccExt for RCC {
+    fn constrain(self) -> Rcc {
+        Rcc { cfgr: CFGR::new() }
+    }
+}
+
+/// Constrained RCC peripheral
+pub struct Rcc {
+    pub cfgr: CFGR,
+}
+
+const HSI: u32 = 16_000_000; // Hz
+const HSE_MIN: u32 = 4_000_000; // Hz
+const HSE_MAX: u32 = 26_000_000; // Hz
+const HSE_BYPASS_MIN: u32 = 1_000_000; // Hz
+const HSE_BYPASS_MAX: u32 = 50_000_000; // Hz
+
+pub struct CFGR {
+    hse: Option<u32>,
+    hse_bypass: bool,
+    css: bool,
+    hclk: Option<u32>,
+    pclk1: Option<u32>,
+    pclk2: Option<u32>,
+    sysclk: Option<u32>,
+    pll48clk: bool,
+    i2s_clk: Option<u32>,
+    i2s_ckin: Option<u32>,
+    vdd: VddRange,
+    prefetch: bool,
+    icache: bool,
+    dcache: bool,
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    sai_clk: Option<u32>,
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    lcd_clk: Option<u32>,
+}
+
+impl Default for CFGR {
+    fn default() -> Self {
+        CFGR::new()
+    }
+}
+
+impl CFGR {
+    /// Returns the reset clock configuration, running everything from the HSI.
+    ///
+    /// `Rcc::constrain` already provides one of these, use this to build the configuration
+    /// passed to [`reconfigure`](#method.reconfigure).
+    pub fn new() -> Self {
+        CFGR {
+            hse: None,
+            hse_bypass: false,
+            css: false,
+            hclk: None,
+            pclk1: None,
+            pclk2: None,
+            sysclk: None,
+            pll48clk: false,
+            i2s_clk: None,
+            i2s_ckin: None,
+            vdd: VddRange::V2_7ToV3_6,
+            prefetch: true,
+            icache: true,
+            dcache: true,
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f446",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            sai_clk: None,
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            lcd_clk: None,
+        }
+    }
+
+    /// Uses HSE (external oscillator) instead of HSI (internal RC oscillator) as the clock source.
+    /// Will result in a hang if an external oscillator is not connected or it fails to start.
+    pub fn use_hse<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.hse = Some(freq.into().0);
+        self
+    }
+
+    /// Bypasses the HSE oscillator, the HSE frequency is then provided by an external clock
+    /// source on OSC_IN, such as a TCXO. Only has an effect together with
+    /// [`use_hse`](#method.use_hse).
+    pub fn bypass(mut self) -> Self {
+        self.hse_bypass = true;
+        self
+    }
+
+    /// Enables the Clock Security System.
+    ///
+    /// If the HSE fails, the hardware switches the system clock to the HSI and raises an NMI, see
+    /// [`handle_css_nmi`](fn.handle_css_nmi.html). Only has an effect together with
+    /// [`use_hse`](#method.use_hse).
+    pub fn enable_css(mut self) -> Self {
+        self.css = true;
+        self
+    }
+
+    pub fn hclk<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.hclk = Some(freq.into().0);
+        self
+    }
+
+    pub fn pclk1<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.pclk1 = Some(freq.into().0);
+        self
+    }
+
+    pub fn pclk2<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.pclk2 = Some(freq.into().0);
+        self
+    }
+
+    pub fn sysclk<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.sysclk = Some(freq.into().0);
+        self
+    }
+
+    /// Requires a valid 48 MHz clock for USB, SDIO and the RNG.
+    ///
+    /// On the STM32F446 and STM32F469/F479 the clock is taken from the PLLSAI if the main PLL can
+    /// not produce both the system clock and an accurate 48 MHz clock, for example with a
+    /// 180 MHz system clock.
+    pub fn require_pll48clk(mut self) -> Self {
+        self.pll48clk = true;
+        self
+    }
+
+    /// Sets the I2S kernel clock, generated by the PLLI2S.
+    ///
+    /// The I2S prescaler of the SPI peripheral divides this clock down to the audio sample rate,
+    /// so it should be a multiple of the sample rate, e.g. 86.016 MHz for 48 kHz and
+    /// 90.3168 MHz for 44.1 kHz. On the STM32F410, which has no PLLI2S, the clock is taken from
+    /// the R output of the main PLL.
+    pub fn i2s_clk<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.i2s_clk = Some(freq.into().0);
+        self.i2s_ckin = None;
+        self
+    }
+
+    /// Sets the SAI kernel clock, generated by the PLLSAI.
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    pub fn sai_clk<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.sai_clk = Some(freq.into().0);
+        self
+    }
+
+    /// Sets the LCD-TFT pixel clock, generated by the PLLSAI.
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    pub fn lcd_clk<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.lcd_clk = Some(freq.into().0);
+        self
+    }
+
+    /// Sets the supply voltage range, which determines the number of flash wait states.
+    ///
+    /// Defaults to 2.7 V to 3.6 V.
+    pub fn vdd_range(mut self, vdd: VddRange) -> Self {
+        self.vdd = vdd;
+        self
+    }
+
+    /// Enables or disables the flash prefetch buffer, enabled by default.
+    ///
+    /// The prefetch buffer is always disabled below 2.1 V.
+    pub fn prefetch(mut self, enable: bool) -> Self {
+        self.prefetch = enable;
+        self
+    }
+
+    /// Enables or disables the flash instruction cache, enabled by default.
+    pub fn icache(mut self, enable: bool) -> Self {
+        self.icache = enable;
+        self
+    }
+
+    /// Enables or disables the flash data cache, enabled by default.
+    pub fn dcache(mut self, enable: bool) -> Self {
+        self.dcache = enable;
+        self
+    }
+
+    /// Uses the external clock on the I2S_CKIN pin as the I2S kernel clock.
+    pub fn i2s_ckin<F>(mut self, freq: F) -> Self
+    where
+        F: Into<Hertz>,
+    {
+        self.i2s_ckin = Some(freq.into().0);
+        self.i2s_clk = None;
+        self
+    }
+
+    fn flash_setup(&self, latency: u8, prefetch: bool) {
+        use crate::stm32::FLASH;
+
+        let flash = unsafe { &*FLASH::ptr() };
+
+        // The caches can only be reset while they are disabled
+        flash
+            .acr
+            .modify(|_, w| w.icen().clear_bit().dcen().clear_bit());
+        flash
+            .acr
+            .modify(|_, w| w.icrst().set_bit().dcrst().set_bit());
+        flash
+            .acr
+            .modify(|_, w| w.icrst().clear_bit().dcrst().clear_bit());
+
+        flash.acr.modify(|r, w| {
+            // LATENCY is 4 bits wide on the larger devices, but only 3 bits in the PAC
+            unsafe { w.bits((r.bits() & !0xf) | u32::from(latency)) };
+            w.prften().bit(prefetch);
+            w.icen().bit(self.icache);
+            w.dcen().bit(self.dcache)
+        });
+    }
+
+    fn voltage_scale_setup(scale: VoltageScale) {
+        let pwr = unsafe { &*PWR::ptr() };
+
+        PWR::enable();
+
+        // VOS is a single bit on these devices, and missing from the PAC
+        #[cfg(any(
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417"
+        ))]
+        pwr.cr.modify(|r, w| unsafe {
+            const VOS: u32 = 1 << 14;
+            w.bits(match scale {
+                VoltageScale::Scale1 => r.bits() | VOS,
+                _ => r.bits() & !VOS,
+            })
+        });
+
+        #[cfg(not(any(
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417"
+        )))]
+        pwr.cr.modify(|_, w| unsafe {
+            w.vos().bits(match scale {
+                VoltageScale::Scale3 => 0b01,
+                VoltageScale::Scale2 => 0b10,
+                VoltageScale::Scale1 => 0b11,
+            })
+        });
+    }
+
+    /// Computes the clock tree for the given device `limits` without touching any register.
+    ///
+    /// The PLL is configured to get as close as possible to the requested frequencies, use
+    /// [`try_freeze`](#method.try_freeze) to reject configurations that are not met exactly.
+    pub fn solve(&self, limits: &Limits) -> Result<ClockSetup, ClockError> {
+        if let Some(hse) = self.hse {
+            let (hse_min, hse_max) = if self.hse_bypass {
+                (HSE_BYPASS_MIN, HSE_BYPASS_MAX)
+            } else {
+                (HSE_MIN, HSE_MAX)
+            };
+            if hse < hse_min || hse > hse_max {
+                return Err(ClockError::HseOutOfRange { freq: Hertz(hse) });
+            }
+        }
+
+        let pllsrcclk = self.hse.unwrap_or(HSI);
+        let sysclk = self.sysclk.unwrap_or(pllsrcclk);
+        let sysclk_on_pll = sysclk != pllsrcclk;
+
+        if sysclk_on_pll && (sysclk > limits.sysclk_max || sysclk < limits.sysclk_min) {
+            return Err(ClockError::SysclkOutOfRange {
+                requested: Hertz(sysclk),
+            });
+        }
+
+        // Let the PLLSAI produce the 48 MHz clock if the main PLL can not produce it together
+        // with the system clock
+        #[cfg(any(feature = "stm32f446", feature = "stm32f469", feature = "stm32f479"))]
+        let pll48_on_sai = self.pll48clk
+            && sysclk_on_pll
+            && MainPll::setup(pllsrcclk, Some(sysclk), false, limits)
+                .map(|pll| !pll::pll48clk_valid(pll.pll48clk))
+                .unwrap_or(false);
+        #[cfg(not(any(feature = "stm32f446", feature = "stm32f469", feature = "stm32f479")))]
+        let pll48_on_sai = false;
+        let pll48_on_main = self.pll48clk && !pll48_on_sai;
+
+        let pll = if sysclk_on_pll || pll48_on_main {
+            let pll = MainPll::setup(
+                pllsrcclk,
+                if sysclk_on_pll { Some(sysclk) } else { None },
+                pll48_on_main,
+                limits,
+            )
+            .ok_or(ClockError::SysclkUnreachable {
+                requested: Hertz(sysclk),
+                achieved: Hertz(0),
+            })?;
+            Some(pll)
+        } else {
+            None
+        };
+
+        // Without a dedicated input divider the PLLI2S and PLLSAI share the one of the main PLL
+        #[cfg(any(
+            feature = "stm32f401",
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417",
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        let shared_pllm = pll
+            .map(|pll| pll.pllm)
+            .unwrap_or_else(|| pll::default_pllm(pllsrcclk));
+
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f446",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        let pllsai = {
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            let lcd_clk = self.lcd_clk;
+            #[cfg(not(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            )))]
+            let lcd_clk = None;
+
+            if pll48_on_sai || self.sai_clk.is_some() || lcd_clk.is_some() {
+                #[cfg(feature = "stm32f446")]
+                let pllm = None;
+                #[cfg(not(feature = "stm32f446"))]
+                let pllm = Some(shared_pllm);
+
+                let pllsai =
+                    SaiPll::setup(pllsrcclk, pllm, pll48_on_sai, self.sai_clk, lcd_clk, limits)
+                        .ok_or(ClockError::PllSaiUnreachable)?;
+                Some(pllsai)
+            } else {
+                None
+            }
+        };
+        #[cfg(not(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f446",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        )))]
+        let pllsai: Option<SaiPll> = None;
+
+        let sysclk = pll.and_then(|pll| pll.sysclk).unwrap_or(sysclk);
+        if sysclk_on_pll && (sysclk > limits.sysclk_max || sysclk < limits.sysclk_min) {
+            return Err(ClockError::SysclkUnreachable {
+                requested: Hertz(self.sysclk.unwrap_or(pllsrcclk)),
+                achieved: Hertz(sysclk),
+            });
+        }
+
+        let i2s = if let Some(freq) = self.i2s_ckin {
+            Some(I2sClock::Ckin(freq))
+        } else if let Some(requested) = self.i2s_clk {
+            let unreachable = ClockError::I2sUnreachable {
+                requested: Hertz(requested),
+            };
+
+            // The STM32F410 has no PLLI2S, the I2S clock is taken from the main PLL
+            #[cfg(feature = "stm32f410")]
+            let i2s = {
+                let pll = pll.ok_or(unreachable)?;
+                let vco_out =
+                    (u64::from(pllsrcclk) * u64::from(pll.plln) / u64::from(pll.pllm)) as u32;
+                let pllr =
+                    core::cmp::min(7, core::cmp::max(2, (vco_out + requested / 2) / requested));
+                I2sClock::PllR {
+                    pllr: pllr as u8,
+                    freq: vco_out / pllr,
+                }
+            };
+
+            #[cfg(not(feature = "stm32f410"))]
+            let i2s = {
+                // Without a dedicated PLLI2SM the input divider is shared with the main PLL
+                #[cfg(any(
+                    feature = "stm32f401",
+                    feature = "stm32f405",
+                    feature = "stm32f407",
+                    feature = "stm32f415",
+                    feature = "stm32f417",
+                    feature = "stm32f427",
+                    feature = "stm32f429",
+                    feature = "stm32f437",
+                    feature = "stm32f439",
+                    feature = "stm32f469",
+                    feature = "stm32f479"
+                ))]
+                let pllm = Some(shared_pllm);
+                #[cfg(any(
+                    feature = "stm32f411",
+                    feature = "stm32f412",
+                    feature = "stm32f413",
+                    feature = "stm32f423",
+                    feature = "stm32f446"
+                ))]
+                let pllm = None;
+
+                I2sClock::PllI2s(
+                    I2sPll::setup(pllsrcclk, pllm, requested, limits).ok_or(unreachable)?,
+                )
+            };
+
+            Some(i2s)
+        } else {
+            None
+        };
+
+        let pll48clk = if pll48_on_sai {
+            pllsai.map(|pllsai| Hertz(pllsai.pll48clk))
+        } else {
+            pll.map(|pll| Hertz(pll.pll48clk))
+        };
+        if self.pll48clk {
+            let achieved = pll48clk.unwrap_or(Hertz(0));
+            if !pll::pll48clk_valid(achieved.0) {
+                return Err(ClockError::Pll48Inaccurate { achieved });
+            }
+        }
+
+        let hclk = self.hclk.unwrap_or(sysclk);
+        let (hpre_bits, hpre_div) = match (sysclk + hclk - 1) / hclk {
+            0 => unreachable!(),
+            1 => (HPRE_A::DIV1, 1),
+            2 => (HPRE_A::DIV2, 2),
+            3..=5 => (HPRE_A::DIV4, 4),
+            6..=11 => (HPRE_A::DIV8, 8),
+            12..=39 => (HPRE_A::DIV16, 16),
+            40..=95 => (HPRE_A::DIV64, 64),
+            96..=191 => (HPRE_A::DIV128, 128),
+            192..=383 => (HPRE_A::DIV256, 256),
+            _ => (HPRE_A::DIV512, 512),
+        };
+
+        // Calculate real AHB clock
+        let hclk = sysclk / hpre_div;
+
+        let pclk1 = self
+            .pclk1
+            .unwrap_or_else(|| core::cmp::min(limits.pclk1_max, hclk));
+        let (ppre1_bits, ppre1) = ppre(hclk, pclk1);
+
+        // Calculate real APB1 clock
+        let pclk1 = hclk / u32::from(ppre1);
+
+        if pclk1 > limits.pclk1_max {
+            return Err(ClockError::PclkTooHigh {
+                bus: Apb::Apb1,
+                achieved: Hertz(pclk1),
+                max: Hertz(limits.pclk1_max),
+            });
+        }
+
+        let pclk2 = self
+            .pclk2
+            .unwrap_or_else(|| core::cmp::min(limits.pclk2_max, hclk));
+        let (ppre2_bits, ppre2) = ppre(hclk, pclk2);
+
+        // Calculate real APB2 clock
+        let pclk2 = hclk / u32::from(ppre2);
+
+        if pclk2 > limits.pclk2_max {
+            return Err(ClockError::PclkTooHigh {
+                bus: Apb::Apb2,
+                achieved: Hertz(pclk2),
+                max: Hertz(limits.pclk2_max),
+            });
+        }
+
+        let (voltage_scale, over_drive) =
+            limits
+                .voltage_scale(hclk, self.vdd)
+                .ok_or(ClockError::SysclkOutOfRange {
+                    requested: Hertz(sysclk),
+                })?;
+
+        let flash_latency =
+            limits
+                .flash_latency(hclk, self.vdd)
+                .ok_or(ClockError::SysclkOutOfRange {
+                    requested: Hertz(sysclk),
+                })?;
+
+        #[cfg(not(feature = "stm32f410"))]
+        let plli2s_clk = match i2s {
+            Some(I2sClock::PllI2s(plli2s)) => Some(Hertz(plli2s.i2s_clk)),
+            _ => None,
+        };
+        #[cfg(feature = "stm32f410")]
+        let plli2s_clk = None;
+
+        Ok(ClockSetup {
+            pll,
+            pllsai,
+            pll48_on_sai,
+            i2s,
+            sysclk_on_pll,
+            hpre_bits,
+            ppre1_bits,
+            ppre2_bits,
+            clocks: Clocks {
+                hclk: Hertz(hclk),
+                pclk1: Hertz(pclk1),
+                pclk2: Hertz(pclk2),
+                ppre1,
+                ppre2,
+                sysclk: Hertz(sysclk),
+                pll48clk,
+                i2s_clk: i2s.map(|i2s| Hertz(i2s.freq())),
+                sai_clk: pllsai.and_then(|pllsai| pllsai.sai_clk).map(Hertz),
+                lcd_clk: pllsai.and_then(|pllsai| pllsai.lcd_clk).map(Hertz),
+                voltage_scale,
+                over_drive,
+                flash_latency,
+                hse: self.hse.map(Hertz),
+                pllclk: pll.map(|pll| {
+                    let vco = u64::from(pllsrcclk) * u64::from(pll.plln) / u64::from(pll.pllm);
+                    Hertz(vco as u32 / u32::from(pll.pllp))
+                }),
+                plli2s_clk,
+                vdd: self.vdd,
+                prefetch: self.prefetch,
+                icache: self.icache,
+                dcache: self.dcache,
+                i2s_ckin: self.i2s_ckin.is_some(),
+            },
+        })
+    }
+
+    /// Applies the clock configuration.
+    ///
+    /// Frequencies the PLL can not produce exactly are rounded to the closest achievable value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration violates a hard limit of the device, see
+    /// [`try_freeze`](#method.try_freeze) for a non panicking version.
+    pub fn freeze(self) -> Clocks {
+        match self.solve(&Limits::DEVICE) {
+            Ok(setup) => self.apply(&setup),
+            Err(e) => panic!("invalid clock configuration: {:?}", e),
+        }
+    }
+
+    /// Applies the clock configuration, or returns an error if the requested system clock can not
+    /// be produced exactly or a limit of the device would be violated.
+    ///
+    /// No register is modified if an error is returned.
+    pub fn try_freeze(self) -> Result<Clocks, ClockError> {
+        let setup = self.try_solve(&Limits::DEVICE)?;
+        Ok(self.apply(&setup))
+    }
+
+    /// Applies the clock configuration at runtime, after the clocks have already been frozen, for
+    /// example to lower the system clock and save power.
+    ///
+    /// The system clock runs from the HSI while the PLLs are reconfigured. Drivers created with
+    /// the previous `Clocks` must be re-timed with their `reclock` method afterwards.
+    ///
+    /// ```no_run
+    /// use stm32f4xx_hal::{prelude::*, rcc::CFGR, stm32};
+    ///
+    /// let dp = stm32::Peripherals::take().unwrap();
+    /// let rcc = dp.RCC.constrain();
+    /// let clocks = rcc.cfgr.use_hse(8.mhz()).sysclk(168.mhz()).freeze();
+    ///
+    /// // Later on, slow down while there is nothing to do
+    /// let clocks = CFGR::new().use_hse(8.mhz()).sysclk(24.mhz()).reconfigure();
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration violates a hard limit of the device, see
+    /// [`try_reconfigure`](#method.try_reconfigure) for a non panicking version.
+    pub fn reconfigure(self) -> Clocks {
+        match self.solve(&Limits::DEVICE) {
+            Ok(setup) => self.reapply(&setup),
+            Err(e) => panic!("invalid clock configuration: {:?}", e),
+        }
+    }
+
+    /// Applies the clock configuration at runtime, or returns an error if the requested system
+    /// clock can not be produced exactly or a limit of the device would be violated.
+    ///
+    /// The current configuration is kept if an error is returned.
+    pub fn try_reconfigure(self) -> Result<Clocks, ClockError> {
+        let setup = self.try_solve(&Limits::DEVICE)?;
+        Ok(self.reapply(&setup))
+    }
+
+    fn reapply(&self, setup: &ClockSetup) -> Clocks {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        reset_to_hsi(rcc);
+
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f446",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        {
+            // Over-drive can only be left while running from the HSI
+            let pwr = unsafe { &*PWR::ptr() };
+            pwr.cr
+                .modify(|_, w| w.odswen().clear_bit().oden().clear_bit());
+        }
+
+        self.apply(setup)
+    }
+
+    /// Solves for the device `limits`, rejecting system clocks that are not met exactly
+    fn try_solve(&self, limits: &Limits) -> Result<ClockSetup, ClockError> {
+        let setup = self.solve(limits)?;
+
+        if let Some(requested) = self.sysclk {
+            if setup.clocks.sysclk.0 != requested {
+                return Err(ClockError::SysclkUnreachable {
+                    requested: Hertz(requested),
+                    achieved: setup.clocks.sysclk,
+                });
+            }
+        }
+
+        Ok(setup)
+    }
+
+    fn apply(&self, setup: &ClockSetup) -> Clocks {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        if let Some(pll) = setup.pll {
+            rcc.pllcfgr.write(|w| unsafe {
+                w.pllm().bits(pll.pllm);
+                w.plln().bits(pll.plln);
+                w.pllp().bits(pll.pllp / 2 - 1);
+                w.pllq().bits(pll.pllq);
+                #[cfg(feature = "stm32f410")]
+                {
+                    if let Some(I2sClock::PllR { pllr, .. }) = setup.i2s {
+                        w.pllr().bits(pllr);
+                    }
+                }
+                w.pllsrc().bit(self.hse.is_some())
+            });
+        }
+
+        // Prefetch is not supported below 2.1 V
+        self.flash_setup(
+            setup.clocks.flash_latency,
+            self.prefetch && self.vdd != VddRange::V1_8ToV2_1,
+        );
+
+        // The voltage scale can only be changed while the PLL is off
+        Self::voltage_scale_setup(setup.clocks.voltage_scale);
+
+        if self.hse.is_some() {
+            // enable HSE and wait for it to be ready
+            rcc.cr.modify(|_, w| w.hsebyp().bit(self.hse_bypass));
+            rcc.cr.modify(|_, w| w.hseon().set_bit());
+            while rcc.cr.read().hserdy().bit_is_clear() {}
+
+            if self.css {
+                rcc.cr.modify(|_, w| w.csson().set_bit());
+            }
+        }
+
+        if setup.pll.is_some() {
+            // Enable PLL
+            rcc.cr.modify(|_, w| w.pllon().set_bit());
+
+            // Wait for PLL to stabilise
+            while rcc.cr.read().pllrdy().bit_is_clear() {}
+
+            // The new voltage scale is applied once the PLL is on
+            let pwr = unsafe { &*PWR::ptr() };
+            while pwr.csr.read().vosrdy().bit_is_clear() {}
+        }
+
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f446",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        {
+            if setup.clocks.over_drive {
+                let pwr = unsafe { &*PWR::ptr() };
+                pwr.cr.modify(|_, w| w.oden().set_bit());
+                while pwr.csr.read().odrdy().bit_is_clear() {}
+                pwr.cr.modify(|_, w| w.odswen().set_bit());
+                while pwr.csr.read().odswrdy().bit_is_clear() {}
+            }
+        }
+
+        if let Some(i2s) = setup.i2s {
+            self.i2s_apply(&i2s);
+        }
+
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f446",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        {
+            if let Some(pllsai) = setup.pllsai {
+                self.pllsai_apply(&pllsai);
+            }
+        }
+
+        #[cfg(feature = "stm32f446")]
+        rcc.dckcfgr2
+            .modify(|_, w| w.ck48msel().bit(setup.pll48_on_sai));
+        #[cfg(any(feature = "stm32f469", feature = "stm32f479"))]
+        rcc.dckcfgr
+            .modify(|_, w| w.ck48msel().bit(setup.pll48_on_sai));
+
+        // Set scaling factors
+        rcc.cfgr.modify(|_, w| unsafe {
+            w.ppre2()
+                .bits(setup.ppre2_bits)
+                .ppre1()
+                .bits(setup.ppre1_bits)
+                .hpre()
+                .variant(setup.hpre_bits)
+        });
+
+        // Wait for the new prescalers to kick in
+        // "The clocks are divided with the new prescaler factor from 1 to 16 AHB cycles after write"
+        cortex_m::asm::delay(16);
+
+        // Select system clock source
+        rcc.cfgr.modify(|_, w| {
+            w.sw().variant(if setup.sysclk_on_pll {
+                SW_A::PLL
+            } else if self.hse.is_some() {
+                SW_A::HSE
+            } else {
+                SW_A::HSI
+            })
+        });
+
+        setup.clocks
+    }
+}
+
+/// Handles a Clock Security System event, call this from the `NMI` exception handler.
+///
+/// Returns `None` if the NMI was not caused by the Clock Security System. Otherwise the CSS flag
+/// is cleared and the clock tree is rebuilt from the HSI, keeping the frequencies of `clocks`
+/// where the HSI allows it. The supply voltage range and the flash settings are kept, and the
+/// PLLI2S and PLLSAI are restarted from the HSI. The returned frequencies should be used to
+/// re-time the drivers.
+pub fn handle_css_nmi(clocks: &Clocks) -> Option<Clocks> {
+    let rcc = unsafe { &*RCC::ptr() };
+
+    if rcc.cir.read().cssf().bit_is_clear() {
+        return None;
+    }
+    // The NMI keeps firing until the flag is cleared
+    rcc.cir.modify(|_, w| w.cssc().set_bit());
+
+    let (cfgr, setup) = css_fallback(clocks, &Limits::DEVICE)?;
+
+    reset_to_hsi(rcc);
+    Some(cfgr.apply(&setup))
+}
+
+/// Returns the configuration to rebuild the clock tree from the HSI after a Clock Security System
+/// event, keeping the supply voltage range, flash settings and frequencies of `clocks`
+fn css_fallback(clocks: &Clocks, limits: &Limits) -> Option<(CFGR, ClockSetup)> {
+    let base = || {
+        let cfgr = CFGR::new()
+            .vdd_range(clocks.vdd)
+            .prefetch(clocks.prefetch)
+            .icache(clocks.icache)
+            .dcache(clocks.dcache);
+        match clocks.i2s_clk {
+            Some(freq) if clocks.i2s_ckin => cfgr.i2s_ckin(freq),
+            _ => cfgr,
+        }
+    };
+
+    let mut cfgr = base();
+    cfgr.sysclk = Some(clocks.sysclk.0);
+    cfgr.hclk = Some(clocks.hclk.0);
+    cfgr.pclk1 = Some(clocks.pclk1.0);
+    cfgr.pclk2 = Some(clocks.pclk2.0);
+    cfgr.pll48clk = clocks.is_pll48clk_valid();
+    if !clocks.i2s_ckin {
+        cfgr.i2s_clk = clocks.i2s_clk.map(|freq| freq.0);
+    }
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    {
+        cfgr.sai_clk = clocks.sai_clk.map(|freq| freq.0);
+    }
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    {
+        cfgr.lcd_clk = clocks.lcd_clk.map(|freq| freq.0);
+    }
+
+    match cfgr.solve(limits) {
+        Ok(setup) => Some((cfgr, setup)),
+        Err(_) => {
+            // Run straight from the HSI, this is always valid
+            let cfgr = base();
+            let setup = cfgr.solve(limits).ok()?;
+            Some((cfgr, setup))
+        }
+    }
+}
+
+/// Switches the system clock to the HSI and stops the PLLs and the HSE, so they can be
+/// reconfigured
+fn reset_to_hsi(rcc: &crate::stm32::rcc::RegisterBlock) {
+    rcc.cr.modify(|_, w| w.hsion().set_bit());
+    while rcc.cr.read().hsirdy().bit_is_clear() {}
+
+    rcc.cfgr.modify(|_, w| w.sw().variant(SW_A::HSI));
+    while rcc.cfgr.read().sws().bits() != 0b00 {}
+
+    rcc.cr.modify(|_, w| w.pllon().clear_bit());
+    while rcc.cr.read().pllrdy().bit_is_set() {}
+
+    // The PLLI2S and PLLSAI can only be configured while they are off, and may share their
+    // input divider with the main PLL
+    #[cfg(not(feature = "stm32f410"))]
+    {
+        rcc.cr.modify(|_, w| w.plli2son().clear_bit());
+        while rcc.cr.read().plli2srdy().bit_is_set() {}
+    }
+
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    {
+        rcc.cr.modify(|_, w| w.pllsaion().clear_bit());
+        while rcc.cr.read().pllsairdy().bit_is_set() {}
+    }
+
+    // HSEBYP can only be changed while the HSE is off
+    rcc.cr
+        .modify(|_, w| w.csson().clear_bit().hseon().clear_bit());
+    while rcc.cr.read().hserdy().bit_is_set() {}
+}
+
+/// Oscillators, PLLs and system clock source that the Stop mode turns off, saved before entering
+/// it so they can be restarted on wakeup
+pub(crate) struct StopState {
+    hse: bool,
+    pll: bool,
+    #[cfg(not(feature = "stm32f410"))]
+    plli2s: bool,
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    pllsai: bool,
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    over_drive: bool,
+    sw: u8,
+}
+
+impl StopState {
+    pub(crate) fn save() -> Self {
+        let rcc = unsafe { &*RCC::ptr() };
+        let cr = rcc.cr.read();
+
+        StopState {
+            hse: cr.hseon().bit_is_set(),
+            pll: cr.pllon().bit_is_set(),
+            #[cfg(not(feature = "stm32f410"))]
+            plli2s: cr.plli2son().bit_is_set(),
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f446",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            pllsai: cr.pllsaion().bit_is_set(),
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f446",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            over_drive: unsafe { &*PWR::ptr() }.cr.read().oden().bit_is_set(),
+            sw: rcc.cfgr.read().sws().bits(),
+        }
+    }
+
+    /// Restarts what was running before the Stop mode, the system clock runs from the HSI on
+    /// wakeup and the PLL settings, prescalers and flash latency are kept.
+    pub(crate) fn restore(&self) {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        if self.hse {
+            rcc.cr.modify(|_, w| w.hseon().set_bit());
+            while rcc.cr.read().hserdy().bit_is_clear() {}
+        }
+
+        if self.pll {
+            rcc.cr.modify(|_, w| w.pllon().set_bit());
+            while rcc.cr.read().pllrdy().bit_is_clear() {}
+        }
+
+        #[cfg(not(feature = "stm32f410"))]
+        {
+            if self.plli2s {
+                rcc.cr.modify(|_, w| w.plli2son().set_bit());
+                while rcc.cr.read().plli2srdy().bit_is_clear() {}
+            }
+        }
+
+        #[cfg(any(
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f446",
+            feature = "stm32f469",
+            feature = "stm32f479"
+        ))]
+        {
+            if self.pllsai {
+                rcc.cr.modify(|_, w| w.pllsaion().set_bit());
+                while rcc.cr.read().pllsairdy().bit_is_clear() {}
+            }
+
+            // Over-drive is left when entering Stop and must be back before switching to the PLL
+            if self.over_drive {
+                let pwr = unsafe { &*PWR::ptr() };
+                pwr.cr.modify(|_, w| w.oden().set_bit());
+                while pwr.csr.read().odrdy().bit_is_clear() {}
+                pwr.cr.modify(|_, w| w.odswen().set_bit());
+                while pwr.csr.read().odswrdy().bit_is_clear() {}
+            }
+        }
+
+        rcc.cfgr.modify(|_, w| unsafe { w.sw().bits(self.sw) });
+        while rcc.cfgr.read().sws().bits() != self.sw {}
+    }
+}
+
+impl CFGR {
+    fn i2s_apply(&self, i2s: &I2sClock) {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        #[cfg(not(feature = "stm32f410"))]
+        {
+            if let I2sClock::PllI2s(plli2s) = i2s {
+                // The PLLI2S runs from the same source as the main PLL
+                rcc.pllcfgr
+                    .modify(|_, w| w.pllsrc().bit(self.hse.is_some()));
+                #[cfg(any(
+                    feature = "stm32f401",
+                    feature = "stm32f405",
+                    feature = "stm32f407",
+                    feature = "stm32f415",
+                    feature = "stm32f417",
+                    feature = "stm32f427",
+                    feature = "stm32f429",
+                    feature = "stm32f437",
+                    feature = "stm32f439",
+                    feature = "stm32f469",
+                    feature = "stm32f479"
+                ))]
+                rcc.pllcfgr
+                    .modify(|_, w| unsafe { w.pllm().bits(plli2s.pllm) });
+
+                rcc.plli2scfgr.write(|w| unsafe {
+                    #[cfg(any(
+                        feature = "stm32f411",
+                        feature = "stm32f412",
+                        feature = "stm32f413",
+                        feature = "stm32f423",
+                        feature = "stm32f446"
+                    ))]
+                    w.plli2sm().bits(plli2s.pllm);
+                    #[cfg(any(
+                        feature = "stm32f412",
+                        feature = "stm32f413",
+                        feature = "stm32f423",
+                        feature = "stm32f427",
+                        feature = "stm32f429",
+                        feature = "stm32f437",
+                        feature = "stm32f439",
+                        feature = "stm32f446",
+                        feature = "stm32f469",
+                        feature = "stm32f479"
+                    ))]
+                    w.plli2sq().bits(plli2s.pllq);
+                    w.plli2sn().bits(plli2s.plln);
+                    w.plli2sr().bits(plli2s.pllr)
+                });
+
+                rcc.cr.modify(|_, w| w.plli2son().set_bit());
+                while rcc.cr.read().plli2srdy().bit_is_clear() {}
+            }
+        }
+
+        let ckin = matches!(i2s, I2sClock::Ckin(_));
+
+        #[cfg(any(
+            feature = "stm32f401",
+            feature = "stm32f405",
+            feature = "stm32f407",
+            feature = "stm32f415",
+            feature = "stm32f417",
+            feature = "stm32f427",
+            feature = "stm32f429",
+            feature = "stm32f437",
+            feature = "stm32f439",
+            feature = "stm32f469",
+            feature = "stm32f479",
+            feature = "stm32f411"
+        ))]
+        rcc.cfgr.modify(|_, w| w.i2ssrc().bit(ckin));
+
+        #[cfg(feature = "stm32f410")]
+        {
+            use crate::stm32::rcc::dckcfgr::I2SSRC_A;
+            rcc.dckcfgr.modify(|_, w| {
+                w.i2ssrc().variant(if ckin {
+                    I2SSRC_A::I2S_CKIN
+                } else {
+                    I2SSRC_A::PLLCLKR
+                })
+            });
+        }
+
+        #[cfg(any(
+            feature = "stm32f412",
+            feature = "stm32f413",
+            feature = "stm32f423",
+            feature = "stm32f446"
+        ))]
+        {
+            use crate::stm32::rcc::dckcfgr::{I2S1SRC_A, I2S2SRC_A};
+            // Both I2S clock domains (APB1 and APB2 I2S instances) use the same source
+            rcc.dckcfgr.modify(|_, w| {
+                if ckin {
+                    w.i2s1src().variant(I2S1SRC_A::I2S_CKIN);
+                    w.i2s2src().variant(I2S2SRC_A::I2S_CKIN)
+                } else {
+                    w.i2s1src().variant(I2S1SRC_A::PLLI2SR);
+                    w.i2s2src().variant(I2S2SRC_A::PLLI2SR)
+                }
+            });
+        }
+    }
+}
+
+impl CFGR {
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f446",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    fn pllsai_apply(&self, pllsai: &SaiPll) {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        // The PLLSAI runs from the same source as the main PLL
+        rcc.pllcfgr
+            .modify(|_, w| w.pllsrc().bit(self.hse.is_some()));
+        #[cfg(not(feature = "stm32f446"))]
+        rcc.pllcfgr
+            .modify(|_, w| unsafe { w.pllm().bits(pllsai.pllm) });
+
+        rcc.pllsaicfgr.write(|w| unsafe {
+            #[cfg(feature = "stm32f446")]
+            w.pllsaim().bits(pllsai.pllm);
+            #[cfg(any(feature = "stm32f446", feature = "stm32f469", feature = "stm32f479"))]
+            w.pllsaip().bits(pllsai.pllp / 2 - 1);
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            w.pllsair().bits(pllsai.pllr);
+            w.pllsain().bits(pllsai.plln);
+            w.pllsaiq().bits(pllsai.pllq)
+        });
+
+        rcc.dckcfgr.modify(|_, w| {
+            #[cfg(any(
+                feature = "stm32f427",
+                feature = "stm32f429",
+                feature = "stm32f437",
+                feature = "stm32f439",
+                feature = "stm32f469",
+                feature = "stm32f479"
+            ))]
+            w.pllsaidivr().bits(match pllsai.divr {
+                2 => 0b00,
+                4 => 0b01,
+                8 => 0b10,
+                _ => 0b11,
+            });
+            w.pllsaidivq().bits(pllsai.divq - 1)
+        });
+
+        if pllsai.sai_clk.is_some() {
+            #[cfg(feature = "stm32f446")]
+            {
+                use crate::stm32::rcc::dckcfgr::{SAI1SRC_A, SAI2SRC_A};
+                rcc.dckcfgr.modify(|_, w| {
+                    w.sai1src().variant(SAI1SRC_A::PLLSAI);
+                    w.sai2src().variant(SAI2SRC_A::PLLSAI)
+                });
+            }
+            #[cfg(not(feature = "stm32f446"))]
+            {
+                use crate::stm32::rcc::dckcfgr::{SAI1ASRC_A, SAI1BSRC_A};
+                rcc.dckcfgr.modify(|_, w| {
+                    w.sai1asrc().variant(SAI1ASRC_A::PLLSAI);
+                    w.sai1bsrc().variant(SAI1BSRC_A::PLLSAI)
+                });
+            }
+        }
+
+        rcc.cr.modify(|_, w| w.pllsaion().set_bit());
+        while rcc.cr.read().pllsairdy().bit_is_clear() {}
+    }
+}
+
+/// Source of the I2S kernel clock
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum I2sClock {
+    /// PLLI2S R output
+    #[cfg(not(feature = "stm32f410"))]
+    PllI2s(I2sPll),
+    /// Main PLL R output
+    #[cfg(feature = "stm32f410")]
+    PllR { pllr: u8, freq: u32 },
+    /// External clock on the I2S_CKIN pin
+    Ckin(u32),
+}
+
+impl I2sClock {
+    fn freq(&self) -> u32 {
+        match *self {
+            #[cfg(not(feature = "stm32f410"))]
+            I2sClock::PllI2s(plli2s) => plli2s.i2s_clk,
+            #[cfg(feature = "stm32f410")]
+            I2sClock::PllR { freq, .. } => freq,
+            I2sClock::Ckin(freq) => freq,
+        }
+    }
+}
+
+fn ppre(hclk: u32, pclk: u32) -> (u8, u8) {
+    match (hclk + pclk - 1) / pclk {
+        0 => unreachable!(),
+        1 => (0b000, 1),
+        2 => (0b100, 2),
+        3..=5 => (0b101, 4),
+        6..=11 => (0b110, 8),
+        _ => (0b111, 16),
+    }
+}
+
+/// Clock tree computed by [`CFGR::solve`](struct.CFGR.html#method.solve)
+#[derive(Clone, Copy, Debug)]
+pub struct ClockSetup {
+    pll: Option<MainPll>,
+    pllsai: Option<SaiPll>,
+    #[cfg_attr(
+        not(any(feature = "stm32f446", feature = "stm32f469", feature = "stm32f479")),
+        allow(dead_code)
+    )]
+    pll48_on_sai: bool,
+    i2s: Option<I2sClock>,
+    sysclk_on_pll: bool,
+    hpre_bits: HPRE_A,
+    ppre1_bits: u8,
+    ppre2_bits: u8,
+    clocks: Clocks,
+}
+
+impl ClockSetup {
+    /// Returns the main PLL configuration, if the PLL is used
+    pub fn pll(&self) -> Option<MainPll> {
+        self.pll
+    }
+
+    /// Returns the PLLSAI configuration, if the PLLSAI is used
+    pub fn pllsai(&self) -> Option<SaiPll> {
+        self.pllsai
+    }
+
+    /// Returns the clock frequencies this configuration results in
+    pub fn clocks(&self) -> Clocks {
+        self.clocks
+    }
+}
+
+/// Regulator voltage scale, trading maximum frequency against power consumption
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VoltageScale {
+    /// Highest frequencies, highest power consumption
+    Scale1,
+    Scale2,
+    /// Lowest frequencies, lowest power consumption
+    Scale3,
+}
+
+/// Supply voltage range
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VddRange {
+    /// 1.8 V to 2.1 V, prefetch is not available
+    V1_8ToV2_1 = 0,
+    /// 2.1 V to 2.4 V
+    V2_1ToV2_4 = 1,
+    /// 2.4 V to 2.7 V
+    V2_4ToV2_7 = 2,
+    /// 2.7 V to 3.6 V
+    V2_7ToV3_6 = 3,
+}
+
+/// APB bus
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Apb {
+    Apb1,
+    Apb2,
+}
+
+/// Clock configuration errors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClockError {
+    /// The HSE frequency is outside of the range supported by the oscillator
+    HseOutOfRange { freq: Hertz },
+    /// The requested system clock is outside of the range supported by the device
+    SysclkOutOfRange { requested: Hertz },
+    /// The PLL can not produce the requested system clock
+    SysclkUnreachable { requested: Hertz, achieved: Hertz },
+    /// The 48 MHz clock used by USB, SDIO and the RNG is not within +-0.25%
+    Pll48Inaccurate { achieved: Hertz },
+    /// No PLL configuration produces the requested I2S clock
+    I2sUnreachable { requested: Hertz },
+    /// No PLLSAI configuration satisfies the requested clocks
+    PllSaiUnreachable,
+    /// An APB clock is above the maximum frequency of its bus
+    PclkTooHigh {
+        bus: Apb,
+        achieved: Hertz,
+        max: Hertz,
+    },
+    /// The source of a clock output is not running
+    McoSourceDisabled,
+    /// The frequency of a clock output is above the maximum of the pin speed
+    McoTooFast { achieved: Hertz, max: Hertz },
+}
+
+/// Clock tree limits of a device family
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Lowest system clock the PLL may produce
+    pub sysclk_min: u32,
+    /// Highest system clock
+    pub sysclk_max: u32,
+    /// Highest APB1 clock
+    pub pclk1_max: u32,
+    /// Highest APB2 clock
+    pub pclk2_max: u32,
+    /// Lowest VCO output frequency
+    pub vco_min: u32,
+    /// Highest VCO output frequency
+    pub vco_max: u32,
+    /// Highest AHB clock in voltage scale 3, 0 if the device has no scale 3
+    pub vos_scale3_max: u32,
+    /// Highest AHB clock in voltage scale 2
+    pub vos_scale2_max: u32,
+    /// Highest AHB clock in voltage scale 1 without over-drive
+    pub vos_scale1_max: u32,
+    /// AHB clock range per flash wait state, indexed by `VddRange`
+    pub flash_latency_step: [u32; 4],
+    /// Highest number of flash wait states
+    pub flash_latency_max: u8,
+}
+
+impl Limits {
+    /// STM32F401
+    pub const F401: Limits = Limits {
+        sysclk_min: 24_000_000,
+        sysclk_max: 84_000_000,
+        pclk1_max: 42_000_000,
+        pclk2_max: 84_000_000,
+        vco_min: 192_000_000,
+        vco_max: 432_000_000,
+        vos_scale3_max: 60_000_000,
+        vos_scale2_max: 84_000_000,
+        vos_scale1_max: 84_000_000,
+        flash_latency_step: [16_000_000, 18_000_000, 24_000_000, 30_000_000],
+        flash_latency_max: 7,
+    };
+
+    /// STM32F405, STM32F407, STM32F415 and STM32F417
+    pub const F40X: Limits = Limits {
+        sysclk_min: 24_000_000,
+        sysclk_max: 168_000_000,
+        pclk1_max: 42_000_000,
+        pclk2_max: 84_000_000,
+        vco_min: 100_000_000,
+        vco_max: 432_000_000,
+        vos_scale3_max: 0,
+        vos_scale2_max: 144_000_000,
+        vos_scale1_max: 168_000_000,
+        flash_latency_step: [20_000_000, 22_000_000, 24_000_000, 30_000_000],
+        flash_latency_max: 7,
+    };
+
+    /// STM32F410, STM32F411 and STM32F412
+    pub const F41X: Limits = Limits {
+        sysclk_min: 24_000_000,
+        sysclk_max: 100_000_000,
+        pclk1_max: 50_000_000,
+        pclk2_max: 100_000_000,
+        vco_min: 100_000_000,
+        vco_max: 432_000_000,
+        vos_scale3_max: 64_000_000,
+        vos_scale2_max: 84_000_000,
+        vos_scale1_max: 100_000_000,
+        flash_latency_step: [16_000_000, 18_000_000, 24_000_000, 30_000_000],
+        flash_latency_max: 7,
+    };
+
+    /// STM32F413 and STM32F423
+    pub const F413: Limits = Limits {
+        flash_latency_step: [16_000_000, 18_000_000, 20_000_000, 25_000_000],
+        ..Limits::F41X
+    };
+
+    /// STM32F427, STM32F429, STM32F437 and STM32F439
+    pub const F42X: Limits = Limits {
+        sysclk_min: 24_000_000,
+        sysclk_max: 180_000_000,
+        pclk1_max: 45_000_000,
+        pclk2_max: 90_000_000,
+        vco_min: 100_000_000,
+        vco_max: 432_000_000,
+        vos_scale3_max: 120_000_000,
+        vos_scale2_max: 144_000_000,
+        vos_scale1_max: 168_000_000,
+        flash_latency_step: [20_000_000, 22_000_000, 24_000_000, 30_000_000],
+        flash_latency_max: 15,
+    };
+
+    /// STM32F446
+    pub const F446: Limits = Limits {
+        sysclk_min: 12_500_000,
+        ..Limits::F42X
+    };
+
+    /// STM32F469 and STM32F479
+    pub const F469: Limits = Limits::F42X;
+
+    /// Returns the lowest voltage scale that supports the AHB clock `hclk`, and whether
+    /// over-drive is needed on top of it, or `None` if over-drive would be needed but is not
+    /// available at the supply voltage `vdd`
+    pub fn voltage_scale(&self, hclk: u32, vdd: VddRange) -> Option<(VoltageScale, bool)> {
+        if hclk > self.vos_scale1_max {
+            // Over-drive is not available below 2.1 V
+            if vdd == VddRange::V1_8ToV2_1 {
+                None
+            } else {
+                Some((VoltageScale::Scale1, true))
+            }
+        } else if hclk > self.vos_scale2_max {
+            Some((VoltageScale::Scale1, false))
+        } else if hclk > self.vos_scale3_max {
+            Some((VoltageScale::Scale2, false))
+        } else {
+            Some((VoltageScale::Scale3, false))
+        }
+    }
+
+    /// Returns the number of flash wait states needed for the AHB clock `hclk` at the supply
+    /// voltage `vdd`, or `None` if the clock is too high for that voltage
+    pub fn flash_latency(&self, hclk: u32, vdd: VddRange) -> Option<u8> {
+        let latency = (hclk - 1) / self.flash_latency_step[vdd as usize];
+        if latency > u32::from(self.flash_latency_max) {
+            None
+        } else {
+            Some(latency as u8)
+        }
+    }
+
+    /// Limits of the selected device
+    #[cfg(feature = "stm32f401")]
+    pub const DEVICE: Limits = Limits::F401;
+
+    /// Limits of the selected device
+    #[cfg(any(
+        feature = "stm32f405",
+        feature = "stm32f407",
+        feature = "stm32f415",
+        feature = "stm32f417"
+    ))]
+    pub const DEVICE: Limits = Limits::F40X;
+
+    /// Limits of the selected device
+    #[cfg(any(feature = "stm32f410", feature = "stm32f411", feature = "stm32f412"))]
+    pub const DEVICE: Limits = Limits::F41X;
+
+    /// Limits of the selected device
+    #[cfg(any(feature = "stm32f413", feature = "stm32f423"))]
+    pub const DEVICE: Limits = Limits::F413;
+
+    /// Limits of the selected device
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439"
+    ))]
+    pub const DEVICE: Limits = Limits::F42X;
+
+    /// Limits of the selected device
+    #[cfg(feature = "stm32f446")]
+    pub const DEVICE: Limits = Limits::F446;
+
+    /// Limits of the selected device
+    #[cfg(any(feature = "stm32f469", feature = "stm32f479"))]
+    pub const DEVICE: Limits = Limits::F469;
+}
+
+/// Frozen clock frequencies
+///
+/// The existence of this value indicates that the clock configuration can no longer be changed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clocks {
+    hclk: Hertz,
+    pclk1: Hertz,
+    pclk2: Hertz,
+    ppre1: u8,
+    ppre2: u8,
+    sysclk: Hertz,
+    pll48clk: Option<Hertz>,
+    i2s_clk: Option<Hertz>,
+    sai_clk: Option<Hertz>,
+    lcd_clk: Option<Hertz>,
+    voltage_scale: VoltageScale,
+    over_drive: bool,
+    flash_latency: u8,
+    hse: Option<Hertz>,
+    pllclk: Option<Hertz>,
+    plli2s_clk: Option<Hertz>,
+    vdd: VddRange,
+    prefetch: bool,
+    icache: bool,
+    dcache: bool,
+    i2s_ckin: bool,
+}
+
+impl Clocks {
+    /// Returns the frequency of the AHB1
+    pub fn hclk(&self) -> Hertz {
+        self.hclk
+    }
+
+    /// Returns the frequency of the APB1
+    pub fn pclk1(&self) -> Hertz {
+        self.pclk1
+    }
+
+    /// Returns the frequency of the APB2
+    pub fn pclk2(&self) -> Hertz {
+        self.pclk2
+    }
+
+    /// Returns the prescaler of the APB1
+    pub fn ppre1(&self) -> u8 {
+        self.ppre1
+    }
+
+    /// Returns the prescaler of the APB2
+    pub fn ppre2(&self) -> u8 {
+        self.ppre2
+    }
+
+    /// Returns the system (core) frequency
+    pub fn sysclk(&self) -> Hertz {
+        self.sysclk
+    }
+
+    /// Returns the frequency of the PLL48 clock line
+    pub fn pll48clk(&self) -> Option<Hertz> {
+        self.pll48clk
+    }
+
+    /// Returns the frequency of the I2S kernel clock
+    pub fn i2s_clk(&self) -> Option<Hertz> {
+        self.i2s_clk
+    }
+
+    /// Returns the frequency of the SAI kernel clock generated by the PLLSAI
+    pub fn sai_clk(&self) -> Option<Hertz> {
+        self.sai_clk
+    }
+
+    /// Returns the frequency of the LCD-TFT pixel clock
+    pub fn lcd_clk(&self) -> Option<Hertz> {
+        self.lcd_clk
+    }
+
+    /// Returns the regulator voltage scale
+    pub fn voltage_scale(&self) -> VoltageScale {
+        self.voltage_scale
+    }
+
+    /// Returns true if over-drive is enabled, which is needed above 168 MHz
+    pub fn over_drive(&self) -> bool {
+        self.over_drive
+    }
+
+    /// Returns the number of flash wait states
+    pub fn flash_latency(&self) -> u8 {
+        self.flash_latency
+    }
+
+    /// Returns the frequency of the HSE, if it is running
+    pub(crate) fn hse(&self) -> Option<Hertz> {
+        self.hse
+    }
+
+    /// Returns true if the PLL48 clock is within USB
+    /// specifications. It is required to use the USB functionality.
+    pub fn is_pll48clk_valid(&self) -> bool {
+        // USB specification allow +-0.25%
+        self.pll48clk
+            .map(|freq| pll::pll48clk_valid(freq.0))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::U32Ext;
+
+    const ALL_LIMITS: [Limits; 7] = [
+        Limits::F401,
+        Limits::F40X,
+        Limits::F41X,
+        Limits::F413,
+        Limits::F42X,
+        Limits::F446,
+        Limits::F469,
+    ];
+
+    fn exact(cfgr: CFGR, limits: &Limits) -> Clocks {
+        cfgr.try_solve(limits).unwrap().clocks()
+    }
+
+    #[test]
+    fn exact_sysclk() {
+        let clocks = exact(
+            CFGR::new()
+                .use_hse(25.mhz())
+                .sysclk(84.mhz())
+                .require_pll48clk(),
+            &Limits::F401,
+        );
+        assert_eq!(clocks.sysclk(), Hertz(84_000_000));
+        assert_eq!(clocks.pclk1(), Hertz(42_000_000));
+        assert_eq!(clocks.pclk2(), Hertz(84_000_000));
+        assert_eq!(clocks.pll48clk(), Some(Hertz(48_000_000)));
+
+        let clocks = exact(
+            CFGR::new()
+                .use_hse(8.mhz())
+                .sysclk(168.mhz())
+                .require_pll48clk(),
+            &Limits::F40X,
+        );
+        assert_eq!(clocks.sysclk(), Hertz(168_000_000));
+        assert_eq!(clocks.pclk1(), Hertz(42_000_000));
+        assert_eq!(clocks.pclk2(), Hertz(84_000_000));
+        assert_eq!(clocks.pll48clk(), Some(Hertz(48_000_000)));
+
+        for limits in &[Limits::F41X, Limits::F413] {
+            let clocks = exact(CFGR::new().use_hse(25.mhz()).sysclk(100.mhz()), limits);
+            assert_eq!(clocks.sysclk(), Hertz(100_000_000));
+            assert_eq!(clocks.pclk1(), Hertz(50_000_000));
+            assert_eq!(clocks.pclk2(), Hertz(100_000_000));
+        }
+
+        for limits in &[Limits::F42X, Limits::F446, Limits::F469] {
+            let clocks = exact(CFGR::new().use_hse(8.mhz()).sysclk(180.mhz()), limits);
+            assert_eq!(clocks.sysclk(), Hertz(180_000_000));
+            assert_eq!(clocks.pclk1(), Hertz(45_000_000));
+            assert_eq!(clocks.pclk2(), Hertz(90_000_000));
+        }
+
+        // Without a PLL everything runs from the HSI
+        for limits in &ALL_LIMITS {
+            let setup = CFGR::new().solve(limits).unwrap();
+            assert_eq!(setup.pll(), None);
+            assert_eq!(setup.clocks().sysclk(), Hertz(HSI));
+        }
+    }
+
+    #[test]
+    fn sysclk_errors() {
+        // The PLL input can not be brought up to 1 MHz
+        assert_eq!(
+            CFGR::new()
+                .use_hse(1.mhz())
+                .bypass()
+                .sysclk(84.mhz())
+                .solve(&Limits::F401)
+                .unwrap_err(),
+            ClockError::SysclkUnreachable {
+                requested: Hertz(84_000_000),
+                achieved: Hertz(0),
+            }
+        );
+
+        // Close, but not exact
+        for limits in &ALL_LIMITS {
+            let requested = limits.sysclk_max - 1;
+            let cfgr = CFGR::new().sysclk(requested.hz());
+            assert!(cfgr.solve(limits).is_ok());
+            match cfgr.try_solve(limits) {
+                Err(ClockError::SysclkUnreachable {
+                    requested: r,
+                    achieved,
+                }) => {
+                    assert_eq!(r, Hertz(requested));
+                    assert_ne!(achieved, Hertz(requested));
+                }
+                other => panic!("{:?}", other.map(|setup| setup.clocks())),
+            }
+        }
+
+        for limits in &ALL_LIMITS {
+            for &requested in &[limits.sysclk_min - 1, limits.sysclk_max + 1] {
+                assert_eq!(
+                    CFGR::new()
+                        .sysclk(requested.hz())
+                        .solve(limits)
+                        .unwrap_err(),
+                    ClockError::SysclkOutOfRange {
+                        requested: Hertz(requested),
+                    }
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn pclk_too_high() {
+        for limits in &ALL_LIMITS {
+            let cfgr = CFGR::new()
+                .sysclk(limits.sysclk_max.hz())
+                .pclk1(limits.pclk2_max.hz());
+            assert_eq!(
+                cfgr.solve(limits).unwrap_err(),
+                ClockError::PclkTooHigh {
+                    bus: Apb::Apb1,
+                    achieved: Hertz(limits.pclk2_max),
+                    max: Hertz(limits.pclk1_max),
+                }
+            );
+        }
+
+        let cfgr = CFGR::new().sysclk(180.mhz()).pclk2(180.mhz());
+        assert_eq!(
+            cfgr.solve(&Limits::F42X).unwrap_err(),
+            ClockError::PclkTooHigh {
+                bus: Apb::Apb2,
+                achieved: Hertz(180_000_000),
+                max: Hertz(90_000_000),
+            }
+        );
+    }
+
+    #[test]
+    fn pll48_inaccurate() {
+        // No multiple of 48 MHz within the VCO range
+        let limits = Limits {
+            vco_min: 300_000_000,
+            vco_max: 301_000_000,
+            ..Limits::F40X
+        };
+        let cfgr = CFGR::new().sysclk(150.mhz()).require_pll48clk();
+        assert!(matches!(
+            cfgr.solve(&limits),
+            Err(ClockError::Pll48Inaccurate { .. })
+        ));
+
+        assert!(pll::pll48clk_valid(48_120_000));
+        assert!(!pll::pll48clk_valid(48_120_001));
+        assert!(pll::pll48clk_valid(47_880_000));
+        assert!(!pll::pll48clk_valid(47_879_999));
+    }
+
+    #[test]
+    fn hse_out_of_range() {
+        let solve = |cfgr: CFGR| cfgr.solve(&Limits::F40X).map(|setup| setup.clocks());
+
+        assert!(solve(CFGR::new().use_hse(4.mhz())).is_ok());
+        assert!(solve(CFGR::new().use_hse(26.mhz())).is_ok());
+        assert_eq!(
+            solve(CFGR::new().use_hse(3_999_999.hz())),
+            Err(ClockError::HseOutOfRange {
+                freq: Hertz(3_999_999)
+            })
+        );
+        assert_eq!(
+            solve(CFGR::new().use_hse(27.mhz())),
+            Err(ClockError::HseOutOfRange {
+                freq: Hertz(27_000_000)
+            })
+        );
+
+        // An external clock may be slower and faster than a crystal
+        assert!(solve(CFGR::new().use_hse(1.mhz()).bypass()).is_ok());
+        assert!(solve(CFGR::new().use_hse(50.mhz()).bypass()).is_ok());
+        assert_eq!(
+            solve(CFGR::new().use_hse(51.mhz()).bypass()),
+            Err(ClockError::HseOutOfRange {
+                freq: Hertz(51_000_000)
+            })
+        );
+    }
+
+    #[cfg(not(feature = "stm32f410"))]
+    #[test]
+    fn i2s_clk() {
+        for &hse in &[8, 25] {
+            for &requested in &[86_016_000, 49_152_000] {
+                let setup = CFGR::new()
+                    .use_hse(hse.mhz())
+                    .sysclk(84.mhz())
+                    .i2s_clk(requested.hz())
+                    .solve(&Limits::F401)
+                    .unwrap();
+                let achieved = setup.clocks().i2s_clk().unwrap().0;
+                assert!(achieved.max(requested) - achieved.min(requested) <= requested / 1000);
+            }
+        }
+
+        let clocks = CFGR::new()
+            .use_hse(8.mhz())
+            .sysclk(168.mhz())
+            .i2s_clk(86_016.khz())
+            .solve(&Limits::F40X)
+            .unwrap()
+            .clocks();
+        assert_eq!(clocks.i2s_clk(), Some(Hertz(86_000_000)));
+
+        let clocks = CFGR::new()
+            .i2s_ckin(12_288.khz())
+            .solve(&Limits::F40X)
+            .unwrap()
+            .clocks();
+        assert_eq!(clocks.i2s_clk(), Some(Hertz(12_288_000)));
+    }
+
+    #[test]
+    fn i2s_unreachable() {
+        for &requested in &[1_000_000, 500_000_000] {
+            assert_eq!(
+                CFGR::new()
+                    .use_hse(8.mhz())
+                    .i2s_clk(requested.hz())
+                    .solve(&Limits::F40X)
+                    .unwrap_err(),
+                ClockError::I2sUnreachable {
+                    requested: Hertz(requested)
+                }
+            );
+        }
+    }
+
+    #[cfg(any(feature = "stm32f446", feature = "stm32f469", feature = "stm32f479"))]
+    #[test]
+    fn pll48clk_from_pllsai() {
+        let setup = CFGR::new()
+            .use_hse(8.mhz())
+            .sysclk(180.mhz())
+            .require_pll48clk()
+            .try_solve(&Limits::DEVICE)
+            .unwrap();
+        assert_eq!(setup.clocks().sysclk(), Hertz(180_000_000));
+        assert_eq!(setup.clocks().pll48clk(), Some(Hertz(48_000_000)));
+        assert_eq!(
+            setup.pllsai().map(|pllsai| pllsai.pll48clk),
+            Some(48_000_000)
+        );
+
+        // The main PLL is used while it can produce both
+        let setup = CFGR::new()
+            .use_hse(8.mhz())
+            .sysclk(168.mhz())
+            .require_pll48clk()
+            .try_solve(&Limits::DEVICE)
+            .unwrap();
+        assert_eq!(setup.clocks().pll48clk(), Some(Hertz(48_000_000)));
+        assert_eq!(setup.pllsai(), None);
+    }
+
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    #[test]
+    fn sai_and_lcd_clk() {
+        let cfgr = || CFGR::new().use_hse(8.mhz()).sysclk(180.mhz());
+
+        let clocks = cfgr()
+            .sai_clk(12_288.khz())
+            .solve(&Limits::DEVICE)
+            .unwrap()
+            .clocks();
+        let sai_clk = clocks.sai_clk().unwrap().0;
+        assert!(sai_clk.max(12_288_000) - sai_clk.min(12_288_000) <= 12_288);
+        assert_eq!(clocks.lcd_clk(), None);
+
+        let clocks = cfgr()
+            .lcd_clk(9_500.khz())
+            .solve(&Limits::DEVICE)
+            .unwrap()
+            .clocks();
+        assert_eq!(clocks.lcd_clk(), Some(Hertz(9_500_000)));
+        assert_eq!(clocks.sai_clk(), None);
+    }
+
+    #[test]
+    fn voltage_scale_thresholds() {
+        use VddRange::*;
+        use VoltageScale::*;
+
+        let table: &[(Limits, u32, VddRange, Option<(VoltageScale, bool)>)] = &[
+            (Limits::F401, 60_000_000, V2_7ToV3_6, Some((Scale3, false))),
+            (Limits::F401, 60_000_001, V2_7ToV3_6, Some((Scale2, false))),
+            (Limits::F401, 84_000_000, V2_7ToV3_6, Some((Scale2, false))),
+            (Limits::F40X, 16_000_000, V2_7ToV3_6, Some((Scale2, false))),
+            (Limits::F40X, 144_000_000, V2_7ToV3_6, Some((Scale2, false))),
+            (Limits::F40X, 144_000_001, V2_7ToV3_6, Some((Scale1, false))),
+            (Limits::F40X, 168_000_000, V2_7ToV3_6, Some((Scale1, false))),
+            (Limits::F41X, 64_000_000, V2_7ToV3_6, Some((Scale3, false))),
+            (Limits::F41X, 84_000_000, V2_7ToV3_6, Some((Scale2, false))),
+            (Limits::F41X, 100_000_000, V2_7ToV3_6, Some((Scale1, false))),
+            (Limits::F413, 100_000_000, V2_7ToV3_6, Some((Scale1, false))),
+            (Limits::F42X, 120_000_000, V2_7ToV3_6, Some((Scale3, false))),
+            (Limits::F42X, 144_000_000, V2_7ToV3_6, Some((Scale2, false))),
+            (Limits::F42X, 144_000_001, V2_7ToV3_6, Some((Scale1, false))),
+            (Limits::F42X, 168_000_000, V2_7ToV3_6, Some((Scale1, false))),
+            (Limits::F42X, 168_000_000, V1_8ToV2_1, Some((Scale1, false))),
+            (Limits::F42X, 168_000_001, V2_7ToV3_6, Some((Scale1, true))),
+            (Limits::F42X, 168_000_001, V2_1ToV2_4, Some((Scale1, true))),
+            (Limits::F42X, 168_000_001, V1_8ToV2_1, None),
+            (Limits::F42X, 180_000_000, V2_7ToV3_6, Some((Scale1, true))),
+            (Limits::F42X, 180_000_000, V2_1ToV2_4, Some((Scale1, true))),
+            (Limits::F42X, 180_000_000, V1_8ToV2_1, None),
+            (Limits::F446, 144_000_000, V2_7ToV3_6, Some((Scale2, false))),
+            (Limits::F446, 168_000_000, V2_7ToV3_6, Some((Scale1, false))),
+            (Limits::F446, 168_000_000, V1_8ToV2_1, Some((Scale1, false))),
+            (Limits::F446, 180_000_000, V2_7ToV3_6, Some((Scale1, true))),
+            (Limits::F446, 180_000_000, V2_1ToV2_4, Some((Scale1, true))),
+            (Limits::F446, 180_000_000, V1_8ToV2_1, None),
+            (Limits::F469, 168_000_000, V2_7ToV3_6, Some((Scale1, false))),
+            (Limits::F469, 168_000_000, V1_8ToV2_1, Some((Scale1, false))),
+            (Limits::F469, 180_000_000, V2_7ToV3_6, Some((Scale1, true))),
+            (Limits::F469, 180_000_000, V2_1ToV2_4, Some((Scale1, true))),
+            (Limits::F469, 180_000_000, V1_8ToV2_1, None),
+        ];
+        for &(limits, hclk, vdd, expected) in table {
+            assert_eq!(
+                limits.voltage_scale(hclk, vdd),
+                expected,
+                "{} Hz at {:?}",
+                hclk,
+                vdd
+            );
+        }
+
+        // Over-drive is rejected below 2.1 V, the flash alone would allow 180 MHz
+        for limits in &[Limits::F42X, Limits::F446, Limits::F469] {
+            assert_eq!(
+                CFGR::new()
+                    .sysclk(180.mhz())
+                    .vdd_range(V1_8ToV2_1)
+                    .solve(limits)
+                    .unwrap_err(),
+                ClockError::SysclkOutOfRange {
+                    requested: Hertz(180_000_000)
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn flash_latency_per_vdd() {
+        use VddRange::*;
+
+        const VDD: [VddRange; 4] = [V1_8ToV2_1, V2_1ToV2_4, V2_4ToV2_7, V2_7ToV3_6];
+
+        // Wait states at 30, 60 and 90 MHz for each supply voltage range
+        let table: &[(Limits, [[u8; 3]; 4])] = &[
+            (Limits::F401, [[1, 3, 5], [1, 3, 4], [1, 2, 3], [0, 1, 2]]),
+            (Limits::F40X, [[1, 2, 4], [1, 2, 4], [1, 2, 3], [0, 1, 2]]),
+            (Limits::F41X, [[1, 3, 5], [1, 3, 4], [1, 2, 3], [0, 1, 2]]),
+            (Limits::F413, [[1, 3, 5], [1, 3, 4], [1, 2, 4], [1, 2, 3]]),
+            (Limits::F42X, [[1, 2, 4], [1, 2, 4], [1, 2, 3], [0, 1, 2]]),
+            (Limits::F446, [[1, 2, 4], [1, 2, 4], [1, 2, 3], [0, 1, 2]]),
+            (Limits::F469, [[1, 2, 4], [1, 2, 4], [1, 2, 3], [0, 1, 2]]),
+        ];
+        for (limits, latencies) in table {
+            for (&vdd, latencies) in VDD.iter().zip(latencies) {
+                for (&hclk, &latency) in [30_000_000, 60_000_000, 90_000_000].iter().zip(latencies)
+                {
+                    assert_eq!(
+                        limits.flash_latency(hclk, vdd),
+                        Some(latency),
+                        "{} Hz at {:?}",
+                        hclk,
+                        vdd
+                    );
+                }
+            }
+        }
+
+        // One more wait state right above each step
+        assert_eq!(Limits::F40X.flash_latency(30_000_001, V2_7ToV3_6), Some(1));
+        assert_eq!(Limits::F40X.flash_latency(168_000_000, V2_7ToV3_6), Some(5));
+        assert_eq!(Limits::F40X.flash_latency(168_000_000, V2_4ToV2_7), Some(6));
+        assert_eq!(Limits::F40X.flash_latency(168_000_000, V2_1ToV2_4), Some(7));
+        // Above the highest number of wait states
+        assert_eq!(Limits::F40X.flash_latency(168_000_000, V1_8ToV2_1), None);
+        assert_eq!(Limits::F42X.flash_latency(180_000_000, V2_7ToV3_6), Some(5));
+    }
+
+    #[test]
+    fn css_fallback_keeps_settings() {
+        let clocks = CFGR::new()
+            .use_hse(8.mhz())
+            .sysclk(120.mhz())
+            .vdd_range(VddRange::V1_8ToV2_1)
+            .prefetch(false)
+            .dcache(false)
+            .solve(&Limits::F40X)
+            .unwrap()
+            .clocks();
+        assert_eq!(clocks.flash_latency(), 5);
+
+        let (cfgr, setup) = css_fallback(&clocks, &Limits::F40X).unwrap();
+        assert_eq!(cfgr.hse, None);
+        assert_eq!(cfgr.vdd, VddRange::V1_8ToV2_1);
+        assert!(!cfgr.prefetch);
+        assert!(cfgr.icache);
+        assert!(!cfgr.dcache);
+        assert_eq!(setup.clocks().sysclk(), Hertz(120_000_000));
+        assert_eq!(setup.clocks().flash_latency(), 5);
+
+        // Falling back to the HSI alone still keeps them
+        let limits = Limits {
+            vco_max: 0,
+            ..Limits::F40X
+        };
+        let (cfgr, setup) = css_fallback(&clocks, &limits).unwrap();
+        assert_eq!(cfgr.vdd, VddRange::V1_8ToV2_1);
+        assert!(!cfgr.prefetch);
+        assert_eq!(setup.pll(), None);
+    }
+
+    #[cfg(not(feature = "stm32f410"))]
+    #[test]
+    fn css_fallback_keeps_i2s() {
+        let clocks = CFGR::new()
+            .use_hse(8.mhz())
+            .sysclk(168.mhz())
+            .i2s_clk(86_016.khz())
+            .solve(&Limits::F40X)
+            .unwrap()
+            .clocks();
+        let (_, setup) = css_fallback(&clocks, &Limits::F40X).unwrap();
+        assert_eq!(setup.clocks().i2s_clk(), clocks.i2s_clk());
+
+        let clocks = CFGR::new()
+            .use_hse(8.mhz())
+            .i2s_ckin(12_288.khz())
+            .solve(&Limits::F40X)
+            .unwrap()
+            .clocks();
+        let (cfgr, _) = css_fallback(&clocks, &Limits::F40X).unwrap();
+        assert_eq!(cfgr.i2s_ckin, Some(12_288_000));
+        assert_eq!(cfgr.i2s_clk, None);
+    }
+
+    #[cfg(any(
+        feature = "stm32f427",
+        feature = "stm32f429",
+        feature = "stm32f437",
+        feature = "stm32f439",
+        feature = "stm32f469",
+        feature = "stm32f479"
+    ))]
+    #[test]
+    fn css_fallback_keeps_pllsai() {
+        let clocks = CFGR::new()
+            .use_hse(8.mhz())
+            .sysclk(168.mhz())
+            .lcd_clk(9_500.khz())
+            .solve(&Limits::DEVICE)
+            .unwrap()
+            .clocks();
+        let (_, setup) = css_fallback(&clocks, &Limits::DEVICE).unwrap();
+        assert_eq!(setup.clocks().lcd_clk(), Some(Hertz(9_500_000)));
+    }
+
+    #[test]
+    fn main_pll_within_limits() {
+        for limits in &ALL_LIMITS {
+            for pllsrcclk in (2..=50).map(|mhz| mhz * 1_000_000) {
+                for &sysclk in &[limits.sysclk_min, 48_000_000, limits.sysclk_max] {
+                    for &pll48clk in &[false, true] {
+                        let pll =
+                            match pll::MainPll::setup(pllsrcclk, Some(sysclk), pll48clk, limits) {
+                                Some(pll) => pll,
+                                None => continue,
+                            };
+
+                        assert!((2..=63).contains(&pll.pllm));
+                        assert!((50..=432).contains(&pll.plln));
+                        assert!([2, 4, 6, 8].contains(&pll.pllp));
+                        assert!((2..=15).contains(&pll.pllq));
+
+                        let vco_in = pllsrcclk / u32::from(pll.pllm);
+                        assert!((1_000_000..=2_000_000).contains(&vco_in));
+                        let vco_out = (u64::from(pllsrcclk) * u64::from(pll.plln)
+                            / u64::from(pll.pllm)) as u32;
+                        assert!((limits.vco_min..=limits.vco_max).contains(&vco_out));
+                        assert!(pll.sysclk.unwrap() <= limits.sysclk_max);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn main_pll_boundaries() {
+        let limits = Limits {
+            sysclk_min: 0,
+            sysclk_max: 1_000_000_000,
+            vco_min: 0,
+            vco_max: 1_000_000_000,
+            ..Limits::F40X
+        };
+        let setup = |sysclk| pll::MainPll::setup(2_000_000, Some(sysclk), false, &limits);
+
+        // The PLL input is 1 MHz, so PLLN is the VCO output in MHz
+        assert_eq!(setup(216_000_000).map(|pll| pll.plln), Some(432));
+        assert_eq!(setup(216_500_000), None);
+        assert_eq!(setup(6_250_000).map(|pll| pll.plln), Some(50));
+        assert_eq!(setup(6_000_000), None);
+
+        // The VCO output limits of the devices
+        let setup =
+            |sysclk, limits: &Limits| pll::MainPll::setup(16_000_000, Some(sysclk), false, limits);
+        let pll = setup(24_000_000, &Limits::F401).unwrap();
+        assert_eq!((pll.pllp, pll.sysclk), (8, Some(24_000_000)));
+        let pll = setup(24_000_000, &Limits::F40X).unwrap();
+        assert_eq!((pll.pllp, pll.sysclk), (6, Some(24_000_000)));
+    }
+}
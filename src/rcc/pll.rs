@@ -0,0 +1,482 @@
+//! PLL parameter solvers
+//!
+//! Everything in this module is plain arithmetic on frequencies, no register is touched.
+
+use super::Limits;
+
+/// Main PLL configuration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MainPll {
+    /// Division factor for the PLL input clock, 2 to 63
+    pub pllm: u8,
+    /// Multiplication factor for the VCO, 50 to 432
+    pub plln: u16,
+    /// Division factor for the system clock, one of 2, 4, 6 or 8
+    pub pllp: u8,
+    /// Division factor for the 48 MHz clock, 2 to 15
+    pub pllq: u8,
+    /// Resulting system clock in Hz, `None` if the PLL does not drive the system clock
+    pub sysclk: Option<u32>,
+    /// Resulting 48 MHz clock in Hz
+    pub pll48clk: u32,
+}
+
+impl MainPll {
+    /// Finds the PLL parameters that best approximate `sysclk` (if the PLL is to drive the system
+    /// clock) and, if `pll48clk` is set, a valid 48 MHz clock for USB, SDIO and the RNG.
+    ///
+    /// Returns `None` if no combination of parameters keeps the PLL within the `limits` of the
+    /// device.
+    pub fn setup(
+        pllsrcclk: u32,
+        sysclk: Option<u32>,
+        pll48clk: bool,
+        limits: &Limits,
+    ) -> Option<Self> {
+        // Input divisor from PLL source clock, must result to frequency in
+        // the range from 1 to 2 MHz
+        let pllm_min = core::cmp::max(2, (pllsrcclk + 1_999_999) / 2_000_000);
+        let pllm_max = core::cmp::min(63, pllsrcclk / 1_000_000);
+
+        let mut best: Option<(Self, (bool, u32, u32))> = None;
+        let mut consider = |pllm: u32, plln: u32, pllp: u32| {
+            if !(50..=432).contains(&plln) {
+                return;
+            }
+            let vco_out = (u64::from(pllsrcclk) * u64::from(plln) / u64::from(pllm)) as u32;
+            if vco_out < limits.vco_min || vco_out > limits.vco_max {
+                return;
+            }
+
+            let pllq = core::cmp::min(15, core::cmp::max(2, (vco_out + 47_999_999) / 48_000_000));
+            let pll48_out = vco_out / pllq;
+            let pll48_diff = diff(pll48_out, 48_000_000);
+            let sysclk_out = vco_out / pllp;
+            if sysclk.is_some() && sysclk_out > limits.sysclk_max {
+                return;
+            }
+            let sysclk_diff = sysclk.map(|f| diff(sysclk_out, f)).unwrap_or(0);
+
+            // A valid 48 MHz clock is a hard requirement, then get as close as possible to the
+            // requested system clock. The lowest PLLM wins on ties.
+            let key = (
+                pll48clk && !pll48clk_valid(pll48_out),
+                sysclk_diff,
+                if pll48clk { pll48_diff } else { 0 },
+            );
+            if best.as_ref().map(|(_, k)| key < *k).unwrap_or(true) {
+                best = Some((
+                    MainPll {
+                        pllm: pllm as u8,
+                        plln: plln as u16,
+                        pllp: pllp as u8,
+                        pllq: pllq as u8,
+                        sysclk: sysclk.map(|_| sysclk_out),
+                        pll48clk: pll48_out,
+                    },
+                    key,
+                ));
+            }
+        };
+
+        for pllm in pllm_min..=pllm_max {
+            let pllp_range: &[u32] = if sysclk.is_some() {
+                &[2, 4, 6, 8]
+            } else {
+                &[2]
+            };
+            for &pllp in pllp_range {
+                if let Some(sysclk) = sysclk {
+                    let plln = div_round(u64::from(sysclk) * u64::from(pllm * pllp), pllsrcclk);
+                    consider(pllm, plln, pllp);
+                }
+                if pll48clk {
+                    // Also try the VCO frequencies that give an exact 48 MHz clock
+                    for pllq in 2..=15u32 {
+                        let plln =
+                            div_round(u64::from(48_000_000 * pllq) * u64::from(pllm), pllsrcclk);
+                        consider(pllm, plln, pllp);
+                    }
+                }
+            }
+        }
+
+        best.map(|(pll, _)| pll)
+    }
+}
+
+/// Returns the input division factor that brings the PLL input closest to 2 MHz, which limits
+/// the PLL jitter
+#[cfg(any(
+    feature = "stm32f401",
+    feature = "stm32f405",
+    feature = "stm32f407",
+    feature = "stm32f415",
+    feature = "stm32f417",
+    feature = "stm32f427",
+    feature = "stm32f429",
+    feature = "stm32f437",
+    feature = "stm32f439",
+    feature = "stm32f469",
+    feature = "stm32f479"
+))]
+pub(crate) fn default_pllm(pllsrcclk: u32) -> u8 {
+    core::cmp::max(2, (pllsrcclk + 1_999_999) / 2_000_000) as u8
+}
+
+/// Returns true if `freq` is within the +-0.25% tolerance USB allows for its 48 MHz clock
+pub(crate) fn pll48clk_valid(freq: u32) -> bool {
+    diff(freq, 48_000_000) <= 48_000_000 * 25 / 10000
+}
+
+fn diff(a: u32, b: u32) -> u32 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+fn div_round(n: u64, d: u32) -> u32 {
+    ((n + u64::from(d) / 2) / u64::from(d)) as u32
+}
+
+/// PLLI2S configuration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct I2sPll {
+    /// Division factor for the PLLI2S input clock, 2 to 63. Shared with the main PLL on devices
+    /// without a dedicated PLLI2SM.
+    pub pllm: u8,
+    /// Multiplication factor for the VCO, 50 to 432
+    pub plln: u16,
+    /// Division factor for the I2S clock, 2 to 7
+    pub pllr: u8,
+    /// Division factor for the SAI clock, 2 to 15. Set to produce the same frequency as the I2S
+    /// clock.
+    pub pllq: u8,
+    /// Resulting I2S clock in Hz
+    pub i2s_clk: u32,
+}
+
+impl I2sPll {
+    /// Finds the PLLI2S parameters that best approximate `i2s_clk`.
+    ///
+    /// `pllm` is the input division factor if it is already fixed by the main PLL, otherwise the
+    /// best one is picked. Returns `None` if no combination of parameters keeps the PLL within
+    /// the `limits` of the device.
+    pub fn setup(pllsrcclk: u32, pllm: Option<u8>, i2s_clk: u32, limits: &Limits) -> Option<Self> {
+        let (pllm_min, pllm_max) = match pllm {
+            Some(pllm) => (u32::from(pllm), u32::from(pllm)),
+            None => (
+                core::cmp::max(2, (pllsrcclk + 1_999_999) / 2_000_000),
+                core::cmp::min(63, pllsrcclk / 1_000_000),
+            ),
+        };
+
+        let mut best: Option<(Self, u32)> = None;
+        for pllm in pllm_min..=pllm_max {
+            for pllr in 2..=7u32 {
+                let plln = div_round(u64::from(i2s_clk) * u64::from(pllm * pllr), pllsrcclk);
+                if !(50..=432).contains(&plln) {
+                    continue;
+                }
+                let vco_out = (u64::from(pllsrcclk) * u64::from(plln) / u64::from(pllm)) as u32;
+                if vco_out < limits.vco_min || vco_out > limits.vco_max {
+                    continue;
+                }
+
+                let i2s_out = vco_out / pllr;
+                let i2s_diff = diff(i2s_out, i2s_clk);
+                if best.as_ref().map(|(_, d)| i2s_diff < *d).unwrap_or(true) {
+                    best = Some((
+                        I2sPll {
+                            pllm: pllm as u8,
+                            plln: plln as u16,
+                            pllr: pllr as u8,
+                            pllq: pllr as u8,
+                            i2s_clk: i2s_out,
+                        },
+                        i2s_diff,
+                    ));
+                }
+            }
+        }
+
+        best.map(|(pll, _)| pll)
+    }
+}
+
+/// PLLSAI configuration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SaiPll {
+    /// Division factor for the PLLSAI input clock, 2 to 63. Shared with the main PLL on devices
+    /// without a dedicated PLLSAIM.
+    pub pllm: u8,
+    /// Multiplication factor for the VCO, 50 to 432
+    pub plln: u16,
+    /// Division factor for the 48 MHz clock, one of 2, 4, 6 or 8
+    pub pllp: u8,
+    /// Division factor for the SAI clock, 2 to 15
+    pub pllq: u8,
+    /// Division factor for the LCD-TFT clock, 2 to 7
+    pub pllr: u8,
+    /// Division factor after `pllq` for the SAI clock, 1 to 32
+    pub divq: u8,
+    /// Division factor after `pllr` for the LCD-TFT clock, one of 2, 4, 8 or 16
+    pub divr: u8,
+    /// Resulting 48 MHz clock in Hz
+    pub pll48clk: u32,
+    /// Resulting SAI clock in Hz, if requested
+    pub sai_clk: Option<u32>,
+    /// Resulting LCD-TFT pixel clock in Hz, if requested
+    pub lcd_clk: Option<u32>,
+}
+
+impl SaiPll {
+    /// Finds the PLLSAI parameters that best approximate the requested SAI clock and LCD-TFT
+    /// pixel clock, and if `pll48clk` is set, a valid 48 MHz clock for USB and SDIO.
+    ///
+    /// `pllm` is the input division factor if it is already fixed by the main PLL, otherwise the
+    /// best one is picked. Returns `None` if no combination of parameters keeps the PLL within
+    /// the `limits` of the device.
+    pub fn setup(
+        pllsrcclk: u32,
+        pllm: Option<u8>,
+        pll48clk: bool,
+        sai_clk: Option<u32>,
+        lcd_clk: Option<u32>,
+        limits: &Limits,
+    ) -> Option<Self> {
+        let (pllm_min, pllm_max) = match pllm {
+            Some(pllm) => (u32::from(pllm), u32::from(pllm)),
+            None => (
+                core::cmp::max(2, (pllsrcclk + 1_999_999) / 2_000_000),
+                core::cmp::min(63, pllsrcclk / 1_000_000),
+            ),
+        };
+
+        let mut best: Option<(Self, (bool, u32, u32, u32))> = None;
+        for pllm in pllm_min..=pllm_max {
+            for plln in 50..=432u32 {
+                let vco_out = (u64::from(pllsrcclk) * u64::from(plln) / u64::from(pllm)) as u32;
+                if vco_out < limits.vco_min || vco_out > limits.vco_max {
+                    continue;
+                }
+
+                // Never exceed 48 MHz, SDIO does not allow it
+                let pllp = core::cmp::min(
+                    8,
+                    core::cmp::max(2, ((vco_out + 47_999_999) / 48_000_000 + 1) & !1),
+                );
+                let pll48_out = vco_out / pllp;
+
+                let (pllq, divq, sai_out) = sai_clk
+                    .map(|f| Self::sai_divider(vco_out, f))
+                    .unwrap_or((2, 1, vco_out / 2));
+                let (pllr, divr, lcd_out) = lcd_clk
+                    .map(|f| Self::lcd_divider(vco_out, f))
+                    .unwrap_or((2, 2, vco_out / 4));
+
+                let key = (
+                    pll48clk && !pll48clk_valid(pll48_out),
+                    sai_clk.map(|f| diff(sai_out, f)).unwrap_or(0),
+                    lcd_clk.map(|f| diff(lcd_out, f)).unwrap_or(0),
+                    if pll48clk {
+                        diff(pll48_out, 48_000_000)
+                    } else {
+                        0
+                    },
+                );
+                if best.as_ref().map(|(_, k)| key < *k).unwrap_or(true) {
+                    best = Some((
+                        SaiPll {
+                            pllm: pllm as u8,
+                            plln: plln as u16,
+                            pllp: pllp as u8,
+                            pllq: pllq as u8,
+                            pllr: pllr as u8,
+                            divq: divq as u8,
+                            divr: divr as u8,
+                            pll48clk: pll48_out,
+                            sai_clk: sai_clk.map(|_| sai_out),
+                            lcd_clk: lcd_clk.map(|_| lcd_out),
+                        },
+                        key,
+                    ));
+                }
+            }
+        }
+
+        best.map(|(pll, _)| pll)
+    }
+
+    /// Returns the PLLSAIQ and PLLSAIDIVQ dividers that bring `vco_out` closest to `freq`, and
+    /// the resulting frequency
+    fn sai_divider(vco_out: u32, freq: u32) -> (u32, u32, u32) {
+        let mut best = (2, 1, vco_out / 2);
+        for pllq in 2..=15 {
+            let divq = vco_out / pllq / freq;
+            for divq in divq..=divq + 1 {
+                let divq = core::cmp::min(32, core::cmp::max(1, divq));
+                let out = vco_out / pllq / divq;
+                if diff(out, freq) < diff(best.2, freq) {
+                    best = (pllq, divq, out);
+                }
+            }
+        }
+        best
+    }
+
+    /// Returns the PLLSAIR and PLLSAIDIVR dividers that bring `vco_out` closest to `freq`, and
+    /// the resulting frequency
+    fn lcd_divider(vco_out: u32, freq: u32) -> (u32, u32, u32) {
+        let mut best = (2, 2, vco_out / 4);
+        for pllr in 2..=7 {
+            for &divr in &[2, 4, 8, 16] {
+                let out = vco_out / pllr / divr;
+                if diff(out, freq) < diff(best.2, freq) {
+                    best = (pllr, divr, out);
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vco_out(pllsrcclk: u32, pllm: u8, plln: u16) -> u32 {
+        (u64::from(pllsrcclk) * u64::from(plln) / u64::from(pllm)) as u32
+    }
+
+    #[test]
+    fn plli2s_audio_clocks() {
+        // PLLM shared with the main PLL, 2 MHz VCO input
+        let pll = I2sPll::setup(8_000_000, Some(4), 86_016_000, &Limits::F40X).unwrap();
+        assert_eq!(pll.pllm, 4);
+        assert_eq!(pll.i2s_clk, 86_000_000);
+        assert_eq!(
+            vco_out(8_000_000, pll.pllm, pll.plln) / u32::from(pll.pllr),
+            pll.i2s_clk
+        );
+
+        let pll = I2sPll::setup(8_000_000, Some(4), 49_152_000, &Limits::F40X).unwrap();
+        assert_eq!((pll.plln, pll.pllr), (172, 7));
+        assert_eq!(pll.i2s_clk, 49_142_857);
+
+        for &pllsrcclk in &[8_000_000, 25_000_000] {
+            for &i2s_clk in &[86_016_000, 49_152_000] {
+                for &pllm in &[None, Some((pllsrcclk + 1_999_999) / 2_000_000)] {
+                    let pll =
+                        I2sPll::setup(pllsrcclk, pllm.map(|m| m as u8), i2s_clk, &Limits::F41X)
+                            .unwrap();
+                    assert!((2..=7).contains(&pll.pllr));
+                    assert_eq!(pll.pllq, pll.pllr);
+                    assert!((50..=432).contains(&pll.plln));
+                    let vco = vco_out(pllsrcclk, pll.pllm, pll.plln);
+                    assert!((100_000_000..=432_000_000).contains(&vco));
+                    assert_eq!(vco / u32::from(pll.pllr), pll.i2s_clk);
+                    // Within 0.1% of the requested frequency
+                    assert!(diff(pll.i2s_clk, i2s_clk) <= i2s_clk / 1000);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn plli2s_unreachable() {
+        // The VCO would have to run below its minimum or above its maximum
+        assert_eq!(
+            I2sPll::setup(8_000_000, None, 1_000_000, &Limits::F40X),
+            None
+        );
+        assert_eq!(
+            I2sPll::setup(8_000_000, None, 500_000_000, &Limits::F40X),
+            None
+        );
+    }
+
+    #[test]
+    fn pllsai_dividers() {
+        let pllsrcclk = 8_000_000;
+
+        let pll = SaiPll::setup(
+            pllsrcclk,
+            Some(4),
+            false,
+            Some(12_288_000),
+            None,
+            &Limits::F42X,
+        )
+        .unwrap();
+        let vco = vco_out(pllsrcclk, pll.pllm, pll.plln);
+        assert!((2..=15).contains(&pll.pllq));
+        assert!((1..=32).contains(&pll.divq));
+        assert_eq!(
+            Some(vco / u32::from(pll.pllq) / u32::from(pll.divq)),
+            pll.sai_clk
+        );
+        assert!(diff(pll.sai_clk.unwrap(), 12_288_000) <= 12_288_000 / 1000);
+        assert_eq!(pll.lcd_clk, None);
+
+        // 9.5 MHz pixel clock of a 480x272 panel
+        let pll = SaiPll::setup(
+            pllsrcclk,
+            Some(4),
+            false,
+            None,
+            Some(9_500_000),
+            &Limits::F42X,
+        )
+        .unwrap();
+        let vco = vco_out(pllsrcclk, pll.pllm, pll.plln);
+        assert!((2..=7).contains(&pll.pllr));
+        assert!([2, 4, 8, 16].contains(&pll.divr));
+        assert_eq!(
+            Some(vco / u32::from(pll.pllr) / u32::from(pll.divr)),
+            pll.lcd_clk
+        );
+        assert_eq!(pll.lcd_clk, Some(9_500_000));
+
+        assert_eq!(
+            SaiPll::sai_divider(344_000_000, 86_000_000),
+            (2, 2, 86_000_000)
+        );
+        assert_eq!(
+            SaiPll::lcd_divider(192_000_000, 6_000_000),
+            (2, 16, 6_000_000)
+        );
+    }
+
+    #[test]
+    fn pllsai_48mhz() {
+        // 180 MHz from a 2 MHz VCO input leaves no PLLQ for 48 MHz on the main PLL
+        let main = MainPll::setup(8_000_000, Some(180_000_000), false, &Limits::F446).unwrap();
+        assert!(!pll48clk_valid(main.pll48clk));
+
+        for &pllm in &[None, Some(main.pllm)] {
+            let pll = SaiPll::setup(8_000_000, pllm, true, None, None, &Limits::F446).unwrap();
+            assert_eq!(pll.pll48clk, 48_000_000);
+            assert!([2, 4, 6, 8].contains(&pll.pllp));
+            assert_eq!(
+                vco_out(8_000_000, pll.pllm, pll.plln) / u32::from(pll.pllp),
+                pll.pll48clk
+            );
+        }
+
+        // The SAI clock is preferred over an exact 48 MHz, as long as it stays within tolerance
+        let pll = SaiPll::setup(
+            8_000_000,
+            Some(4),
+            true,
+            Some(11_289_600),
+            None,
+            &Limits::F446,
+        )
+        .unwrap();
+        assert!(pll48clk_valid(pll.pll48clk));
+        assert!(pll.pll48clk <= 48_000_000);
+    }
+}
@@ -0,0 +1,62 @@
+//! Cause of the last reset
+//!
+//! The reset flags in RCC_CSR are sticky: they accumulate over resets until cleared, so read them
+//! once at boot with [`ResetReason::read_and_clear`].
+//!
+//! ```no_run
+//! use stm32f4xx_hal::rcc::ResetReason;
+//!
+//! let reason = ResetReason::read_and_clear();
+//! if reason.independent_watchdog {
+//!     // the firmware got stuck before the reboot
+//! }
+//! ```
+
+use crate::stm32::RCC;
+
+/// Reset flags of RCC_CSR, more than one can be set at once
+///
+/// A power-on reset also sets `pin` and `brown_out`, since the reset pin is driven low and the
+/// brown-out detector trips while the supply ramps up. Check `por_pdr` first to tell a power
+/// cycle apart from the other causes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResetReason {
+    /// Low-power management reset, entering Stop or Standby with the nRST_STOP/nRST_STDBY option
+    /// bits cleared
+    pub low_power: bool,
+    /// Window watchdog reset
+    pub window_watchdog: bool,
+    /// Independent watchdog reset, see [`IndependentWatchdog`](crate::watchdog::IndependentWatchdog)
+    pub independent_watchdog: bool,
+    /// Software reset through `SCB::sys_reset`
+    pub software: bool,
+    /// Power-on or power-down reset
+    pub por_pdr: bool,
+    /// Reset from the NRST pin
+    pub pin: bool,
+    /// Brown-out reset
+    pub brown_out: bool,
+}
+
+impl ResetReason {
+    /// Reads the reset flags without clearing them
+    pub fn read() -> Self {
+        let csr = unsafe { &(*RCC::ptr()).csr }.read();
+        ResetReason {
+            low_power: csr.lpwrrstf().bit_is_set(),
+            window_watchdog: csr.wwdgrstf().bit_is_set(),
+            independent_watchdog: csr.wdgrstf().bit_is_set(),
+            software: csr.sftrstf().bit_is_set(),
+            por_pdr: csr.porrstf().bit_is_set(),
+            pin: csr.padrstf().bit_is_set(),
+            brown_out: csr.borrstf().bit_is_set(),
+        }
+    }
+
+    /// Reads the reset flags and clears them, so the next reset reports only its own cause
+    pub fn read_and_clear() -> Self {
+        let reason = Self::read();
+        unsafe { &(*RCC::ptr()).csr }.modify(|_, w| w.rmvf().set_bit());
+        reason
+    }
+}
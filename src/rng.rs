@@ -2,23 +2,26 @@ use core::cmp;
 use core::mem;
 
 use crate::hal::blocking::rng;
-use crate::rcc::Clocks;
-use crate::stm32;
+use crate::rcc::{Clocks, Enable, Reset};
 use crate::stm32::RNG;
 use crate::time::U32Ext;
 use core::num::NonZeroU32;
 use core::ops::Shl;
-use rand_core::RngCore;
+use nb::block;
+use rand_core::{CryptoRng, RngCore};
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ErrorKind {
     /// The RNG_CLK was not correctly detected (fRNG_CLK< fHCLK/16).
     /// See CECS in RNG peripheral documentation.
     ClockError = 2,
     /// RNG detected more than 64 consecutive bits of the same value (0 or 1) OR
-    /// more than 32 consecutive 01 pairs.
+    /// more than 32 consecutive 01 pairs, again after restarting the RNG.
     /// See SECS in RNG peripheral documentation.
     SeedError = 4,
+    /// The RNG produced the same word twice in a row, which fails the FIPS 140-2 continuous
+    /// random number generator test.
+    RepeatedWord = 8,
 }
 
 impl From<ErrorKind> for rand_core::Error {
@@ -38,17 +41,12 @@ impl RngExt for RNG {
     /// otherwise all reads of the RNG would return a ClockError (CECS error).
     /// This function will panic if pll48clk < 1/16 hclk.
     fn constrain(self, clocks: Clocks) -> Rng {
-        let rcc = unsafe { &*stm32::RCC::ptr() };
-
         cortex_m::interrupt::free(|_| {
             // enable RNG_CLK (peripheral clock)
-            rcc.ahb2enr.modify(|_, w| w.rngen().enabled());
-            // give RNG_CLK time to start
-            let _ = rcc.ahb2enr.read().rngen().is_enabled();
+            RNG::enable();
 
             // reset the RNG
-            rcc.ahb2rstr.modify(|_, w| w.rngrst().set_bit());
-            rcc.ahb2rstr.modify(|_, w| w.rngrst().clear_bit());
+            RNG::reset();
 
             // verify the clock configuration is valid
             let hclk = clocks.hclk();
@@ -59,37 +57,167 @@ impl RngExt for RNG {
             self.cr.modify(|_, w| w.rngen().set_bit());
         });
 
-        Rng { rb: self }
+        Rng {
+            rb: self,
+            test: ContinuousTest { previous: None },
+            recovering: false,
+        }
     }
 }
 
+/// Random number generator
+///
+/// Seed errors are recovered from by restarting the RNG, and every word goes through the FIPS
+/// 140-2 continuous test before being handed out.
 pub struct Rng {
     rb: RNG,
+    test: ContinuousTest,
+    /// A seed error was recovered from, and no word was handed out since
+    recovering: bool,
 }
 
 impl Rng {
     /// Returns 32 bits of random data from RNDATA, or error.
     /// May fail if, for example RNG_CLK is misconfigured.
     fn next_random_word(&mut self) -> Result<u32, ErrorKind> {
-        loop {
-            let status = self.rb.sr.read();
-            if status.cecs().bit() {
-                return Err(ErrorKind::ClockError);
-            }
-            if status.secs().bit() {
-                return Err(ErrorKind::SeedError);
-            }
-            if status.drdy().bit() {
-                return Ok(self.rb.dr.read().rndata().bits());
+        block!(self.poll_random_word())
+    }
+
+    /// Returns 32 bits of random data if they are ready, handles the errors otherwise
+    fn poll_random_word(&mut self) -> nb::Result<u32, ErrorKind> {
+        let status = self.rb.sr.read();
+        if status.seis().bit_is_set() {
+            // The data register may hold a word without enough entropy. As the reference manual
+            // recommends, clear SEIS and restart the RNG, the first word it then produces is
+            // dropped by the continuous test.
+            self.rb.sr.write(|w| w.seis().clear_bit().ceis().set_bit());
+            self.rb.cr.modify(|_, w| w.rngen().clear_bit());
+            self.rb.cr.modify(|_, w| w.rngen().set_bit());
+            self.test.previous = None;
+
+            return if mem::replace(&mut self.recovering, true) {
+                self.recovering = false;
+                Err(nb::Error::Other(ErrorKind::SeedError))
+            } else {
+                Err(nb::Error::WouldBlock)
+            };
+        }
+        if status.ceis().bit_is_set() {
+            self.rb.sr.write(|w| w.ceis().clear_bit().seis().set_bit());
+        }
+        // The RNG resumes on its own once the clock is fixed
+        if status.cecs().bit_is_set() {
+            return Err(nb::Error::Other(ErrorKind::ClockError));
+        }
+        if status.drdy().bit_is_clear() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        let word = self.rb.dr.read().rndata().bits();
+        match self.test.check(word)? {
+            Some(word) => {
+                self.recovering = false;
+                Ok(word)
             }
+            None => Err(nb::Error::WouldBlock),
         }
     }
 
+    /// Enables the RNG interrupt, raised when a word is ready or on errors, see [`InterruptFill`]
+    pub fn listen(&mut self) {
+        self.rb.cr.modify(|_, w| w.ie().set_bit());
+    }
+
+    /// Disables the RNG interrupt
+    pub fn unlisten(&mut self) {
+        self.rb.cr.modify(|_, w| w.ie().clear_bit());
+    }
+
     pub fn release(self) -> RNG {
         self.rb
     }
 }
 
+/// FIPS 140-2 continuous random number generator test
+struct ContinuousTest {
+    previous: Option<u32>,
+}
+
+impl ContinuousTest {
+    /// Returns `word` if it differs from the previous one, or `None` if it is the first one, which
+    /// is only kept for the comparison
+    fn check(&mut self, word: u32) -> Result<Option<u32>, ErrorKind> {
+        match self.previous.replace(word) {
+            None => Ok(None),
+            Some(previous) if previous == word => Err(ErrorKind::RepeatedWord),
+            Some(_) => Ok(Some(word)),
+        }
+    }
+}
+
+/// Buffer filled from the RNG interrupt, which shares the HASH_RNG vector with the hash processor
+///
+/// ```no_run
+/// use stm32f4xx_hal::{
+///     prelude::*,
+///     rng::{InterruptFill, RngExt},
+///     stm32,
+/// };
+///
+/// let dp = stm32::Peripherals::take().unwrap();
+/// let clocks = dp.RCC.constrain().cfgr.require_pll48clk().freeze();
+/// let mut rng = dp.RNG.constrain(clocks);
+///
+/// let mut fill = InterruptFill::new([0u8; 64]);
+/// rng.listen();
+/// // In the HASH_RNG interrupt handler
+/// if fill.on_interrupt(&mut rng).unwrap() {
+///     let random = fill.release();
+/// }
+/// ```
+pub struct InterruptFill<B> {
+    buffer: B,
+    filled: usize,
+}
+
+impl<B> InterruptFill<B>
+where
+    B: AsMut<[u8]>,
+{
+    /// Fills `buffer` from the start
+    pub fn new(buffer: B) -> Self {
+        InterruptFill { buffer, filled: 0 }
+    }
+
+    /// Takes the words that are ready, returns true once the buffer is full and stops listening
+    /// to the RNG interrupt then
+    pub fn on_interrupt(&mut self, rng: &mut Rng) -> Result<bool, ErrorKind> {
+        while !self.is_full() {
+            let word = match rng.poll_random_word() {
+                Ok(word) => word,
+                Err(nb::Error::WouldBlock) => return Ok(false),
+                Err(nb::Error::Other(error)) => return Err(error),
+            };
+            let buffer = &mut self.buffer.as_mut()[self.filled..];
+            let n = cmp::min(4, buffer.len());
+            buffer[..n].copy_from_slice(&word.to_ne_bytes()[..n]);
+            self.filled += n;
+        }
+        rng.unlisten();
+        Ok(true)
+    }
+
+    /// Returns true once the buffer is full
+    pub fn is_full(&mut self) -> bool {
+        self.filled == self.buffer.as_mut().len()
+    }
+
+    /// Returns the buffer, filled or not
+    pub fn release(self) -> B {
+        self.buffer
+    }
+}
+
 impl rng::Read for Rng {
     type Error = rand_core::Error;
 
@@ -127,3 +255,19 @@ impl RngCore for Rng {
         Ok(())
     }
 }
+
+impl CryptoRng for Rng {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continuous_test() {
+        let mut test = ContinuousTest { previous: None };
+        assert_eq!(test.check(0x1234_5678), Ok(None));
+        assert_eq!(test.check(0x9ABC_DEF0), Ok(Some(0x9ABC_DEF0)));
+        assert_eq!(test.check(0x9ABC_DEF0), Err(ErrorKind::RepeatedWord));
+        assert_eq!(test.check(0), Ok(Some(0)));
+    }
+}
@@ -0,0 +1,794 @@
+//! Real-time clock
+//!
+//! The RTC keeps a BCD calendar in the backup domain, it keeps running through resets, Stop and
+//! Standby as long as VDD or VBAT is present. The calendar is always kept in 24 hour format and
+//! covers the years 2000 to 2099.
+//!
+//! ```no_run
+//! use stm32f4xx_hal::{
+//!     prelude::*,
+//!     pwr::PwrExt,
+//!     rtc::{DateTime, Rtc, RtcClock},
+//!     stm32,
+//! };
+//!
+//! let dp = stm32::Peripherals::take().unwrap();
+//! let clocks = dp.RCC.constrain().cfgr.freeze();
+//! let mut pwr = dp.PWR.constrain();
+//!
+//! let mut rtc = Rtc::new(dp.RTC, RtcClock::Lse { bypass: false }, &mut pwr, &clocks).unwrap();
+//! rtc.set_datetime(&DateTime {
+//!     year: 2020,
+//!     month: 7,
+//!     day: 14,
+//!     hour: 12,
+//!     minute: 30,
+//!     second: 0,
+//! })
+//! .unwrap();
+//! let now = rtc.datetime();
+//! ```
+
+use crate::pwr::Pwr;
+use crate::rcc::Clocks;
+use crate::stm32::{EXTI, RCC, RTC};
+use crate::time::MilliSeconds;
+
+const LSE: u32 = 32_768; // Hz
+const LSI: u32 = 32_000; // Hz
+/// Highest RTC clock when it is taken from the HSE
+const HSE_RTC_MAX: u32 = 1_000_000; // Hz
+
+/// Clock source of the RTC
+///
+/// Changing the source of a running RTC resets the backup domain, which clears the calendar and
+/// the backup registers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RtcClock {
+    /// Low speed external oscillator, a 32.768 kHz crystal or an external clock with `bypass`
+    Lse { bypass: bool },
+    /// Low speed internal oscillator, its frequency varies between 17 and 47 kHz
+    Lsi,
+    /// High speed external oscillator, divided down to 1 MHz or less. The RTC stops in Stop and
+    /// Standby modes with this source.
+    Hse,
+}
+
+/// RTC error
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// The clock source is not running
+    ClockDisabled,
+    /// The clock source can not be divided down to exactly 1 Hz
+    InvalidClock,
+    /// A date, time, alarm, interval or calibration value is out of range
+    InvalidInputData,
+}
+
+/// Day of the week
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weekday {
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6,
+    Sunday = 7,
+}
+
+impl Weekday {
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            1 => Weekday::Monday,
+            2 => Weekday::Tuesday,
+            3 => Weekday::Wednesday,
+            4 => Weekday::Thursday,
+            5 => Weekday::Friday,
+            6 => Weekday::Saturday,
+            _ => Weekday::Sunday,
+        }
+    }
+}
+
+/// Calendar date and time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTime {
+    /// 2000 to 2099
+    pub year: u16,
+    /// 1 to 12
+    pub month: u8,
+    /// 1 to 31
+    pub day: u8,
+    /// 0 to 23
+    pub hour: u8,
+    /// 0 to 59
+    pub minute: u8,
+    /// 0 to 59
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Returns the day of the week
+    pub fn weekday(&self) -> Weekday {
+        // Sakamoto's method, 0 is Sunday
+        const T: [u16; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let y = if self.month < 3 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let d = (y + y / 4 - y / 100 + y / 400 + T[self.month as usize - 1] + self.day as u16) % 7;
+        Weekday::from_bits(if d == 0 { 7 } else { d as u32 })
+    }
+
+    fn is_valid(&self) -> bool {
+        (2000..=2099).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && self.day >= 1
+            && self.day <= days_in_month(self.year, self.month)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    /// Encodes the time and date into the RTC_TR and RTC_DR register layouts
+    fn to_bits(self) -> Result<(u32, u32), Error> {
+        if !self.is_valid() {
+            return Err(Error::InvalidInputData);
+        }
+        let tr = time_to_bits(self.hour, self.minute, self.second);
+        let dr = (bcd((self.year - 2000) as u8) << 16)
+            | ((self.weekday() as u32) << 13)
+            | (bcd(self.month) << 8)
+            | bcd(self.day);
+        Ok((tr, dr))
+    }
+
+    /// Decodes the RTC_TR and RTC_DR register values, in 24 hour format
+    fn from_bits(tr: u32, dr: u32) -> Self {
+        DateTime {
+            year: 2000 + from_bcd(dr >> 16) as u16,
+            month: from_bcd((dr >> 8) & 0x1f),
+            day: from_bcd(dr & 0x3f),
+            hour: from_bcd((tr >> 16) & 0x3f),
+            minute: from_bcd((tr >> 8) & 0x7f),
+            second: from_bcd(tr & 0x7f),
+        }
+    }
+}
+
+/// Time of a timestamp event, the RTC does not record the year
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timestamp {
+    pub month: u8,
+    pub day: u8,
+    pub weekday: Weekday,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Timestamp {
+    /// Decodes the RTC_TSTR and RTC_TSDR register values
+    fn from_bits(tstr: u32, tsdr: u32) -> Self {
+        Timestamp {
+            month: from_bcd((tsdr >> 8) & 0x1f),
+            day: from_bcd(tsdr & 0x3f),
+            weekday: Weekday::from_bits((tsdr >> 13) & 0x7),
+            hour: from_bcd((tstr >> 16) & 0x3f),
+            minute: from_bcd((tstr >> 8) & 0x7f),
+            second: from_bcd(tstr & 0x7f),
+        }
+    }
+}
+
+/// Alarms A and B
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Alarm {
+    A,
+    B,
+}
+
+/// Day an alarm matches on
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlarmDay {
+    /// Every day
+    Any,
+    /// Day of the month, 1 to 31
+    Date(u8),
+    /// Day of the week
+    Weekday(Weekday),
+}
+
+/// Time an alarm matches on, fields left to `None` are masked and match any value
+///
+/// The default matches every second.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AlarmTime {
+    pub day: AlarmDay,
+    pub hour: Option<u8>,
+    pub minute: Option<u8>,
+    pub second: Option<u8>,
+}
+
+impl Default for AlarmTime {
+    fn default() -> Self {
+        AlarmTime {
+            day: AlarmDay::Any,
+            hour: None,
+            minute: None,
+            second: None,
+        }
+    }
+}
+
+impl AlarmTime {
+    /// Encodes the alarm into the RTC_ALRMxR register layout
+    fn to_bits(self) -> Result<u32, Error> {
+        let mut bits = match self.day {
+            AlarmDay::Any => 1 << 31,
+            AlarmDay::Date(day) if (1..=31).contains(&day) => bcd(day) << 24,
+            AlarmDay::Weekday(weekday) => (1 << 30) | ((weekday as u32) << 24),
+            AlarmDay::Date(_) => return Err(Error::InvalidInputData),
+        };
+        bits |= match self.hour {
+            None => 1 << 23,
+            Some(hour) if hour < 24 => bcd(hour) << 16,
+            Some(_) => return Err(Error::InvalidInputData),
+        };
+        bits |= match self.minute {
+            None => 1 << 15,
+            Some(minute) if minute < 60 => bcd(minute) << 8,
+            Some(_) => return Err(Error::InvalidInputData),
+        };
+        bits |= match self.second {
+            None => 1 << 7,
+            Some(second) if second < 60 => bcd(second),
+            Some(_) => return Err(Error::InvalidInputData),
+        };
+        Ok(bits)
+    }
+}
+
+/// Edge of the RTC_TS pin (PC13) that records a timestamp
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimestampEdge {
+    Rising,
+    Falling,
+}
+
+/// RTC interrupt events, routed to the EXTI lines 17 (alarms), 21 (timestamp) and 22 (wakeup)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    AlarmA,
+    AlarmB,
+    Wakeup,
+    Timestamp,
+}
+
+impl Event {
+    fn exti_line(self) -> u8 {
+        match self {
+            Event::AlarmA | Event::AlarmB => 17,
+            Event::Timestamp => 21,
+            Event::Wakeup => 22,
+        }
+    }
+}
+
+/// Real-time clock
+pub struct Rtc {
+    regs: RTC,
+    /// Frequency of the RTC clock, before the prescalers
+    rtcclk: u32,
+}
+
+impl Rtc {
+    /// Enables access to the backup domain, starts the clock source and sets the prescalers for a
+    /// 1 Hz calendar clock.
+    ///
+    /// The calendar keeps running if the RTC was already set up from the same source, for
+    /// example before a reset or a wakeup from Standby.
+    pub fn new(regs: RTC, source: RtcClock, pwr: &mut Pwr, clocks: &Clocks) -> Result<Self, Error> {
+        let rcc = unsafe { &*RCC::ptr() };
+
+        let (rtcclk, rtcpre) = match source {
+            RtcClock::Lse { .. } => (LSE, 0),
+            RtcClock::Lsi => (LSI, 0),
+            RtcClock::Hse => hse_divider(clocks.hse().ok_or(Error::ClockDisabled)?.0)
+                .ok_or(Error::InvalidClock)?,
+        };
+        let (prediv_a, prediv_s) = prescalers(rtcclk).ok_or(Error::InvalidClock)?;
+
+        pwr.enable_backup_access();
+
+        let rtcsel = match source {
+            RtcClock::Lse { .. } => 0b01,
+            RtcClock::Lsi => 0b10,
+            RtcClock::Hse => 0b11,
+        };
+        let bdcr = rcc.bdcr.read();
+        if bdcr.rtcsel().bits() != rtcsel && bdcr.rtcsel().bits() != 0b00 {
+            // The source can only be changed by a backup domain reset
+            rcc.bdcr.modify(|_, w| w.bdrst().set_bit());
+            rcc.bdcr.modify(|_, w| w.bdrst().clear_bit());
+        }
+
+        match source {
+            RtcClock::Lse { bypass } => {
+                if rcc.bdcr.read().lserdy().bit_is_clear() {
+                    rcc.bdcr.modify(|_, w| w.lsebyp().bit(bypass));
+                    rcc.bdcr.modify(|_, w| w.lseon().set_bit());
+                    while rcc.bdcr.read().lserdy().bit_is_clear() {}
+                }
+            }
+            RtcClock::Lsi => {
+                rcc.csr.modify(|_, w| w.lsion().set_bit());
+                while rcc.csr.read().lsirdy().bit_is_clear() {}
+            }
+            RtcClock::Hse => {
+                rcc.cfgr.modify(|_, w| w.rtcpre().bits(rtcpre));
+            }
+        }
+
+        rcc.bdcr
+            .modify(|_, w| w.rtcsel().bits(rtcsel).rtcen().set_bit());
+
+        let mut rtc = Rtc { regs, rtcclk };
+
+        let prer = rtc.regs.prer.read();
+        if rtc.regs.isr.read().inits().bit_is_clear()
+            || prer.prediv_a().bits() != prediv_a
+            || prer.prediv_s().bits() != prediv_s
+        {
+            rtc.init_mode(|regs| {
+                // Both fields must be written separately, the synchronous prescaler first
+                regs.prer
+                    .modify(|_, w| unsafe { w.prediv_s().bits(prediv_s) });
+                regs.prer
+                    .modify(|_, w| unsafe { w.prediv_a().bits(prediv_a) });
+                regs.cr.modify(|_, w| w.fmt().clear_bit());
+            });
+        }
+
+        Ok(rtc)
+    }
+
+    /// Sets the calendar
+    pub fn set_datetime(&mut self, datetime: &DateTime) -> Result<(), Error> {
+        let (tr, dr) = datetime.to_bits()?;
+        self.init_mode(|regs| {
+            regs.tr.write(|w| unsafe { w.bits(tr) });
+            regs.dr.write(|w| unsafe { w.bits(dr) });
+            // The shadow registers are updated on the next RTC clock edge
+            regs.isr.modify(|_, w| w.rsf().clear_bit());
+        });
+        Ok(())
+    }
+
+    /// Reads the calendar
+    ///
+    /// After a wakeup from Stop or Standby, the first read waits for the calendar to be copied
+    /// into the shadow registers, up to two RTC clock periods.
+    pub fn datetime(&mut self) -> DateTime {
+        while self.regs.isr.read().rsf().bit_is_clear() {}
+        // Reading RTC_TR locks RTC_DR until it is read
+        let tr = self.regs.tr.read().bits();
+        let dr = self.regs.dr.read().bits();
+        DateTime::from_bits(tr, dr)
+    }
+
+    /// Sets and enables an alarm, use `listen` to get an interrupt when it fires
+    pub fn set_alarm(&mut self, alarm: Alarm, time: &AlarmTime) -> Result<(), Error> {
+        let bits = time.to_bits()?;
+        self.disable_alarm(alarm);
+        self.unlocked(|regs| match alarm {
+            Alarm::A => {
+                while regs.isr.read().alrawf().bit_is_clear() {}
+                regs.alrmar.write(|w| unsafe { w.bits(bits) });
+                regs.cr.modify(|_, w| w.alrae().set_bit());
+            }
+            Alarm::B => {
+                while regs.isr.read().alrbwf().bit_is_clear() {}
+                regs.alrmbr.write(|w| unsafe { w.bits(bits) });
+                regs.cr.modify(|_, w| w.alrbe().set_bit());
+            }
+        });
+        Ok(())
+    }
+
+    /// Disables an alarm
+    pub fn disable_alarm(&mut self, alarm: Alarm) {
+        self.unlocked(|regs| match alarm {
+            Alarm::A => regs.cr.modify(|_, w| w.alrae().clear_bit()),
+            Alarm::B => regs.cr.modify(|_, w| w.alrbe().clear_bit()),
+        });
+    }
+
+    /// Starts the periodic wakeup timer
+    ///
+    /// Intervals up to 32 s for the LSE are counted in RTC clock cycles divided by 16, longer
+    /// intervals up to 36 hours are counted in seconds.
+    pub fn enable_wakeup(&mut self, interval: MilliSeconds) -> Result<(), Error> {
+        let (wucksel, wut) =
+            wakeup_setup(self.rtcclk, interval.0).ok_or(Error::InvalidInputData)?;
+        self.disable_wakeup();
+        self.unlocked(|regs| {
+            while regs.isr.read().wutwf().bit_is_clear() {}
+            regs.wutr.write(|w| unsafe { w.wut().bits(wut) });
+            regs.cr.modify(|_, w| unsafe { w.wcksel().bits(wucksel) });
+            regs.cr.modify(|_, w| w.wute().set_bit());
+        });
+        Ok(())
+    }
+
+    /// Stops the periodic wakeup timer
+    pub fn disable_wakeup(&mut self) {
+        self.unlocked(|regs| regs.cr.modify(|_, w| w.wute().clear_bit()));
+    }
+
+    /// Records the calendar on an edge of the RTC_TS pin, PC13, whatever its GPIO configuration
+    pub fn enable_timestamp(&mut self, edge: TimestampEdge) {
+        self.unlocked(|regs| {
+            // The edge can only be changed while timestamps are disabled
+            regs.cr.modify(|_, w| w.tse().clear_bit());
+            regs.cr
+                .modify(|_, w| w.tsedge().bit(edge == TimestampEdge::Falling));
+            regs.cr.modify(|_, w| w.tse().set_bit());
+        });
+    }
+
+    /// Stops recording timestamps
+    pub fn disable_timestamp(&mut self) {
+        self.unlocked(|regs| regs.cr.modify(|_, w| w.tse().clear_bit()));
+    }
+
+    /// Returns the last recorded timestamp, if any, and clears it
+    pub fn timestamp(&mut self) -> Option<Timestamp> {
+        if self.regs.isr.read().tsf().bit_is_clear() {
+            return None;
+        }
+        let timestamp =
+            Timestamp::from_bits(self.regs.tstr.read().bits(), self.regs.tsdr.read().bits());
+        self.regs
+            .isr
+            .modify(|_, w| w.tsovf().clear_bit().tsf().clear_bit());
+        Some(timestamp)
+    }
+
+    /// Corrects the RTC clock by `ppm` parts per million with the smooth digital calibration,
+    /// from -487.1 to +488.5 ppm. A positive value speeds the calendar up.
+    pub fn calibrate(&mut self, ppm: f32) -> Result<(), Error> {
+        let (calp, calm) = calibration(ppm).ok_or(Error::InvalidInputData)?;
+        self.unlocked(|regs| {
+            while regs.isr.read().recalpf().bit_is_set() {}
+            regs.calr
+                .write(|w| unsafe { w.calp().bit(calp).calm().bits(calm) });
+        });
+        Ok(())
+    }
+
+    /// Starts listening for an `event`, on the RTC and its EXTI line
+    pub fn listen(&mut self, exti: &mut EXTI, event: Event) {
+        let line = event.exti_line();
+        exti.imr
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << line)) });
+        exti.rtsr
+            .modify(|r, w| unsafe { w.bits(r.bits() | (1 << line)) });
+        self.unlocked(|regs| {
+            regs.cr.modify(|_, w| match event {
+                Event::AlarmA => w.alraie().set_bit(),
+                Event::AlarmB => w.alrbie().set_bit(),
+                Event::Wakeup => w.wutie().set_bit(),
+                Event::Timestamp => w.tsie().set_bit(),
+            })
+        });
+    }
+
+    /// Stops listening for an `event`
+    pub fn unlisten(&mut self, exti: &mut EXTI, event: Event) {
+        self.unlocked(|regs| {
+            regs.cr.modify(|_, w| match event {
+                Event::AlarmA => w.alraie().clear_bit(),
+                Event::AlarmB => w.alrbie().clear_bit(),
+                Event::Wakeup => w.wutie().clear_bit(),
+                Event::Timestamp => w.tsie().clear_bit(),
+            })
+        });
+
+        // Both alarms share their EXTI line
+        let cr = self.regs.cr.read();
+        if cr.alraie().bit_is_set() || cr.alrbie().bit_is_set() {
+            if let Event::AlarmA | Event::AlarmB = event {
+                return;
+            }
+        }
+        let line = event.exti_line();
+        exti.imr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << line)) });
+        exti.rtsr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << line)) });
+    }
+
+    /// Returns `true` if `event` occurred
+    pub fn is_pending(&self, event: Event) -> bool {
+        let isr = self.regs.isr.read();
+        match event {
+            Event::AlarmA => isr.alraf().bit_is_set(),
+            Event::AlarmB => isr.alrbf().bit_is_set(),
+            Event::Wakeup => isr.wutf().bit_is_set(),
+            Event::Timestamp => isr.tsf().bit_is_set(),
+        }
+    }
+
+    /// Clears the flag of `event` and its EXTI pending bit
+    pub fn clear_interrupt(&mut self, event: Event) {
+        self.regs.isr.modify(|_, w| match event {
+            Event::AlarmA => w.alraf().clear_bit(),
+            Event::AlarmB => w.alrbf().clear_bit(),
+            Event::Wakeup => w.wutf().clear_bit(),
+            Event::Timestamp => w.tsf().clear_bit(),
+        });
+        unsafe { (*EXTI::ptr()).pr.write(|w| w.bits(1 << event.exti_line())) };
+    }
+
+    /// Releases the RTC peripheral, it keeps running
+    pub fn release(self) -> RTC {
+        self.regs
+    }
+
+    /// Runs `f` with the write protection of the RTC registers lifted
+    fn unlocked<F: FnOnce(&RTC)>(&mut self, f: F) {
+        self.regs.wpr.write(|w| unsafe { w.bits(0xCA) });
+        self.regs.wpr.write(|w| unsafe { w.bits(0x53) });
+        f(&self.regs);
+        self.regs.wpr.write(|w| unsafe { w.bits(0xFF) });
+    }
+
+    /// Runs `f` with the calendar stopped, so the prescalers and calendar can be written
+    fn init_mode<F: FnOnce(&RTC)>(&mut self, f: F) {
+        self.unlocked(|regs| {
+            regs.isr.modify(|_, w| w.init().set_bit());
+            while regs.isr.read().initf().bit_is_clear() {}
+            f(regs);
+            regs.isr.modify(|_, w| w.init().clear_bit());
+        });
+    }
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Encodes a value from 0 to 99 as two BCD digits
+fn bcd(value: u8) -> u32 {
+    (((value / 10) << 4) | (value % 10)) as u32
+}
+
+/// Decodes two BCD digits
+fn from_bcd(bits: u32) -> u8 {
+    (((bits >> 4) & 0xf) * 10 + (bits & 0xf)) as u8
+}
+
+fn time_to_bits(hour: u8, minute: u8, second: u8) -> u32 {
+    (bcd(hour) << 16) | (bcd(minute) << 8) | bcd(second)
+}
+
+/// Returns the RTC clock and RTCPRE divider for an HSE frequency, the RTC clock must be 1 MHz or
+/// less and divide down to 1 Hz
+fn hse_divider(hse: u32) -> Option<(u32, u8)> {
+    let min = core::cmp::max(2, (hse + HSE_RTC_MAX - 1) / HSE_RTC_MAX);
+    (min..=31)
+        .filter(|div| hse % div == 0)
+        .map(|div| (hse / div, div as u8))
+        .find(|&(rtcclk, _)| prescalers(rtcclk).is_some())
+}
+
+/// Returns the asynchronous and synchronous prescaler values that divide `rtcclk` to exactly
+/// 1 Hz, with the asynchronous prescaler as high as possible to save power
+fn prescalers(rtcclk: u32) -> Option<(u8, u16)> {
+    (1..=128)
+        .rev()
+        .find(|a| rtcclk % a == 0 && rtcclk / a <= 32768)
+        .map(|a| ((a - 1) as u8, (rtcclk / a - 1) as u16))
+}
+
+/// Returns the WUCKSEL and WUTR values for a wakeup interval
+fn wakeup_setup(rtcclk: u32, ms: u32) -> Option<(u8, u16)> {
+    // RTCCLK / 16
+    let ticks = (rtcclk / 16) as u64 * ms as u64 / 1000;
+    if (1..=0x1_0000).contains(&ticks) {
+        return Some((0b000, (ticks - 1) as u16));
+    }
+    // 1 Hz calendar clock, with 2^16 added to WUTR for the longest intervals
+    let seconds = (ms + 500) / 1000;
+    match seconds {
+        1..=0x1_0000 => Some((0b100, (seconds - 1) as u16)),
+        0x1_0001..=0x2_0000 => Some((0b110, (seconds - 0x1_0001) as u16)),
+        _ => None,
+    }
+}
+
+/// Returns the CALP and CALM values for a correction of `ppm`, over the 2^20 RTC clock cycles of
+/// the 32 s calibration window
+fn calibration(ppm: f32) -> Option<(bool, u16)> {
+    let pulses = ppm * (1u32 << 20) as f32 / 1_000_000.;
+    // Round to the nearest pulse
+    let pulses = if pulses < 0. {
+        (pulses - 0.5) as i32
+    } else {
+        (pulses + 0.5) as i32
+    };
+    match pulses {
+        // CALP inserts 512 pulses, CALM masks some of them
+        1..=512 => Some((true, (512 - pulses) as u16)),
+        -511..=0 => Some((false, -pulses as u16)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(year: u16, month: u8, day: u8) -> DateTime {
+        DateTime {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        }
+    }
+
+    #[test]
+    fn bcd_round_trip() {
+        assert_eq!(bcd(0), 0x00);
+        assert_eq!(bcd(7), 0x07);
+        assert_eq!(bcd(42), 0x42);
+        assert_eq!(bcd(99), 0x99);
+        for value in 0..100 {
+            assert_eq!(from_bcd(bcd(value)), value);
+        }
+    }
+
+    #[test]
+    fn weekday() {
+        assert_eq!(datetime(2000, 1, 1).weekday(), Weekday::Saturday);
+        assert_eq!(datetime(2000, 2, 29).weekday(), Weekday::Tuesday);
+        assert_eq!(datetime(2020, 7, 14).weekday(), Weekday::Tuesday);
+        assert_eq!(datetime(2024, 3, 3).weekday(), Weekday::Sunday);
+        assert_eq!(datetime(2099, 12, 31).weekday(), Weekday::Thursday);
+    }
+
+    #[test]
+    fn datetime_bits() {
+        let dt = DateTime {
+            year: 2020,
+            month: 12,
+            day: 31,
+            hour: 23,
+            minute: 59,
+            second: 58,
+        };
+        let (tr, dr) = dt.to_bits().unwrap();
+        assert_eq!(tr, 0x0023_5958);
+        // Thursday
+        assert_eq!(dr, 0x0020_9231);
+        assert_eq!(DateTime::from_bits(tr, dr), dt);
+    }
+
+    #[test]
+    fn datetime_validation() {
+        assert!(datetime(2000, 1, 1).to_bits().is_ok());
+        assert!(datetime(2024, 2, 29).to_bits().is_ok());
+        assert_eq!(
+            datetime(2023, 2, 29).to_bits(),
+            Err(Error::InvalidInputData)
+        );
+        assert_eq!(
+            datetime(2021, 4, 31).to_bits(),
+            Err(Error::InvalidInputData)
+        );
+        assert_eq!(datetime(1999, 1, 1).to_bits(), Err(Error::InvalidInputData));
+        assert_eq!(datetime(2100, 1, 1).to_bits(), Err(Error::InvalidInputData));
+        assert_eq!(datetime(2020, 0, 1).to_bits(), Err(Error::InvalidInputData));
+        assert_eq!(datetime(2020, 1, 0).to_bits(), Err(Error::InvalidInputData));
+        let dt = DateTime {
+            hour: 24,
+            ..datetime(2020, 1, 1)
+        };
+        assert_eq!(dt.to_bits(), Err(Error::InvalidInputData));
+    }
+
+    #[test]
+    fn timestamp_bits() {
+        assert_eq!(
+            Timestamp::from_bits(0x0012_3456, 0x0000_a715),
+            Timestamp {
+                month: 7,
+                day: 15,
+                weekday: Weekday::Friday,
+                hour: 12,
+                minute: 34,
+                second: 56,
+            }
+        );
+    }
+
+    #[test]
+    fn alarm_bits() {
+        assert_eq!(AlarmTime::default().to_bits(), Ok(0x8080_8080));
+        let alarm = AlarmTime {
+            day: AlarmDay::Date(25),
+            hour: Some(6),
+            minute: Some(30),
+            second: Some(0),
+        };
+        assert_eq!(alarm.to_bits(), Ok(0x2506_3000));
+        let alarm = AlarmTime {
+            day: AlarmDay::Weekday(Weekday::Sunday),
+            hour: None,
+            minute: Some(15),
+            second: None,
+        };
+        assert_eq!(alarm.to_bits(), Ok(0x4780_1580));
+        let alarm = AlarmTime {
+            day: AlarmDay::Date(32),
+            ..AlarmTime::default()
+        };
+        assert_eq!(alarm.to_bits(), Err(Error::InvalidInputData));
+        let alarm = AlarmTime {
+            second: Some(60),
+            ..AlarmTime::default()
+        };
+        assert_eq!(alarm.to_bits(), Err(Error::InvalidInputData));
+    }
+
+    #[test]
+    fn prescaler_values() {
+        assert_eq!(prescalers(32_768), Some((127, 255)));
+        assert_eq!(prescalers(32_000), Some((127, 249)));
+        assert_eq!(prescalers(1_000_000), Some((124, 7999)));
+        // Prime, can not be divided
+        assert_eq!(prescalers(32_771), None);
+    }
+
+    #[test]
+    fn hse_dividers() {
+        assert_eq!(hse_divider(8_000_000), Some((1_000_000, 8)));
+        assert_eq!(hse_divider(25_000_000), Some((1_000_000, 25)));
+        assert_eq!(hse_divider(1_000_000), Some((500_000, 2)));
+        // Needs a divider above 31
+        assert_eq!(hse_divider(50_000_000), None);
+    }
+
+    #[test]
+    fn wakeup_values() {
+        // 2048 Hz
+        assert_eq!(wakeup_setup(32_768, 1000), Some((0b000, 2047)));
+        assert_eq!(wakeup_setup(32_768, 32_000), Some((0b000, 65535)));
+        assert_eq!(wakeup_setup(32_768, 60_000), Some((0b100, 59)));
+        assert_eq!(wakeup_setup(32_768, 100_000_000), Some((0b110, 34463)));
+        assert_eq!(wakeup_setup(32_768, 0), None);
+        assert_eq!(wakeup_setup(32_768, 200_000_000), None);
+    }
+
+    #[test]
+    fn calibration_values() {
+        assert_eq!(calibration(0.), Some((false, 0)));
+        assert_eq!(calibration(-10.), Some((false, 10)));
+        assert_eq!(calibration(488.), Some((true, 0)));
+        assert_eq!(calibration(100.), Some((true, 407)));
+        assert_eq!(calibration(-487.), Some((false, 511)));
+        assert_eq!(calibration(500.), None);
+        assert_eq!(calibration(-500.), None);
+    }
+}
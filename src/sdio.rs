@@ -1,10 +1,9 @@
 //! Sdio host
 
-use crate::bb;
 #[allow(unused_imports)]
 use crate::gpio::{gpioa::*, gpiob::*, gpioc::*, gpiod::*, Alternate, AF12};
-use crate::rcc::Clocks;
-use crate::stm32::{RCC, SDIO};
+use crate::rcc::{Clocks, Enable, Reset};
+use crate::stm32::SDIO;
 pub use sdio_host::{
     CardCapacity, CardStatus, CurrentState, SDStatus, CIC, CID, CSD, OCR, RCA, SCR,
 };
@@ -190,14 +189,9 @@ pub struct Card {
 impl Sdio {
     /// Create and enable the Sdio device
     pub fn new<PINS: Pins>(sdio: SDIO, _pins: PINS, clocks: Clocks) -> Self {
-        unsafe {
-            //NOTE(unsafe) this reference will only be used for atomic writes with no side effects
-            let rcc = &*RCC::ptr();
-            // Enable and reset the sdio peripheral, it's the same bit position for both registers
-            bb::set(&rcc.apb2enr, 11);
-            bb::set(&rcc.apb2rstr, 11);
-            bb::clear(&rcc.apb2rstr, 11);
-        }
+        // Enable and reset the sdio peripheral
+        SDIO::enable();
+        SDIO::reset();
 
         // Configure clock
         sdio.clkcr.write(|w| unsafe {
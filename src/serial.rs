@@ -25,7 +25,7 @@ use nb::block;
     feature = "stm32f469",
     feature = "stm32f479"
 ))]
-use crate::stm32::{RCC, USART1, USART2, USART6};
+use crate::stm32::{USART1, USART2, USART6};
 
 #[cfg(any(
     feature = "stm32f405",
@@ -372,7 +372,8 @@ use crate::gpio::gpiog::{PG14, PG9};
 #[cfg(any(feature = "stm32f413", feature = "stm32f423"))]
 use crate::gpio::AF11;
 use crate::gpio::{Alternate, AF7, AF8};
-use crate::rcc::Clocks;
+use crate::rcc::{BusClock, Clocks, Enable};
+use crate::time::Bps;
 
 /// Serial error
 #[derive(Debug)]
@@ -1374,6 +1375,7 @@ impl PinRx<UART10> for PG11<Alternate<AF11>> {}
 pub struct Serial<USART, PINS> {
     usart: USART,
     pins: PINS,
+    baudrate: Bps,
 }
 
 /// Serial receiver
@@ -1388,7 +1390,7 @@ pub struct Tx<USART> {
 
 macro_rules! halUsartImpl {
     ($(
-        $USARTX:ident: ($usartX:ident, $apbXenr:ident, $usartXen:ident,  $pclkX:ident),
+        $USARTX:ident: $usartX:ident,
     )+) => {
         $(
             impl<PINS> Serial<$USARTX, PINS> {
@@ -1403,14 +1405,11 @@ macro_rules! halUsartImpl {
                 {
                     use self::config::*;
 
-                    // NOTE(unsafe) This executes only during initialisation
-                    let rcc = unsafe { &(*RCC::ptr()) };
-
                     // Enable clock for USART
-                    rcc.$apbXenr.modify(|_, w| w.$usartXen().set_bit());
+                    $USARTX::enable();
 
                     // Calculate correct baudrate divisor on the fly
-                    let div = (clocks.$pclkX().0 + config.baudrate.0 / 2)
+                    let div = ($USARTX::clock(&clocks).0 + config.baudrate.0 / 2)
                         / config.baudrate.0;
                     usart.brr.write(|w| unsafe { w.bits(div) });
 
@@ -1442,7 +1441,12 @@ macro_rules! halUsartImpl {
                             })
                     });
 
-                    Ok(Serial { usart, pins }.config_stop(config))
+                    Ok(Serial {
+                        usart,
+                        pins,
+                        baudrate: config.baudrate,
+                    }
+                    .config_stop(config))
                 }
 
                 /// Starts listening for an interrupt event
@@ -1503,6 +1507,15 @@ macro_rules! halUsartImpl {
                 pub fn release(self) -> ($USARTX, PINS) {
                     (self.usart, self.pins)
                 }
+
+                /// Recomputes the baud rate divisor after the clocks have been reconfigured with
+                /// [`CFGR::reconfigure`](../rcc/struct.CFGR.html#method.reconfigure).
+                ///
+                /// Must not be called while a transfer is ongoing.
+                pub fn reclock(&mut self, clocks: &Clocks) {
+                    let div = ($USARTX::clock(clocks).0 + self.baudrate.0 / 2) / self.baudrate.0;
+                    self.usart.brr.write(|w| unsafe { w.bits(div) });
+                }
             }
 
             impl<PINS> serial::Read<u8> for Serial<$USARTX, PINS> {
@@ -1601,7 +1614,7 @@ macro_rules! halUsartImpl {
 
 macro_rules! halUsart {
     ($(
-        $USARTX:ident: ($usartX:ident, $apbXenr:ident, $usartXen:ident, $pclkX:ident),
+        $USARTX:ident: $usartX:ident,
     )+) => {
         $(
         impl<PINS> Serial<$USARTX, PINS> {
@@ -1623,7 +1636,7 @@ macro_rules! halUsart {
         )+
 
         halUsartImpl! {
-            $( $USARTX: ($usartX, $apbXenr, $usartXen, $pclkX), )+
+            $( $USARTX: $usartX, )+
         }
     }
 }
@@ -1643,7 +1656,7 @@ macro_rules! halUsart {
 ))]
 macro_rules! halUart {
     ($(
-        $USARTX:ident: ($usartX:ident, $apbXenr:ident, $usartXen:ident, $pclkX:ident),
+        $USARTX:ident: $usartX:ident,
     )+) => {
         $(
         impl<PINS> Serial<$USARTX, PINS> {
@@ -1665,7 +1678,7 @@ macro_rules! halUart {
         )+
 
         halUsartImpl! {
-            $( $USARTX: ($usartX, $apbXenr, $usartXen, $pclkX), )+
+            $( $USARTX: $usartX, )+
         }
     }
 }
@@ -1690,9 +1703,9 @@ macro_rules! halUart {
     feature = "stm32f479"
 ))]
 halUsart! {
-    USART1: (usart1, apb2enr, usart1en, pclk2),
-    USART2: (usart2, apb1enr, usart2en, pclk1),
-    USART6: (usart6, apb2enr, usart6en, pclk2),
+    USART1: usart1,
+    USART2: usart2,
+    USART6: usart6,
 }
 
 #[cfg(any(
@@ -1712,7 +1725,7 @@ halUsart! {
     feature = "stm32f479"
 ))]
 halUsart! {
-    USART3: (usart3, apb1enr, usart3en, pclk1),
+    USART3: usart3,
 }
 
 #[cfg(any(
@@ -1729,14 +1742,14 @@ halUsart! {
     feature = "stm32f479"
 ))]
 halUart! {
-    UART4: (uart4, apb1enr, uart4en, pclk1),
-    UART5: (uart5, apb1enr, uart5en, pclk1),
+    UART4: uart4,
+    UART5: uart5,
 }
 
 #[cfg(any(feature = "stm32f413", feature = "stm32f423"))]
 halUsart! {
-    UART4: (uart4, apb1enr, uart4en, pclk1),
-    UART5: (uart5, apb1enr, uart5en, pclk1),
+    UART4: uart4,
+    UART5: uart5,
 }
 
 #[cfg(any(
@@ -1750,14 +1763,14 @@ halUsart! {
     feature = "stm32f479"
 ))]
 halUsart! {
-    UART7: (uart7, apb1enr, uart7en, pclk1),
-    UART8: (uart8, apb1enr, uart8en, pclk1),
+    UART7: uart7,
+    UART8: uart8,
 }
 
 #[cfg(any(feature = "stm32f413", feature = "stm32f423"))]
 halUsart! {
-    UART9: (uart9, apb2enr, uart9en, pclk2),
-    UART10: (uart10, apb2enr, uart10en, pclk2),
+    UART9: uart9,
+    UART10: uart10,
 }
 
 impl<USART> fmt::Write for Tx<USART>
@@ -2,7 +2,9 @@
 //!
 //! (stored in flash memory)
 
-use core::str::from_utf8_unchecked;
+use core::str::{from_utf8, from_utf8_unchecked};
+
+use crate::stm32::DBGMCU;
 
 /// This is the test voltage, in millivolts of the calibration done at the factory
 pub const VDDA_CALIB: u32 = 3300;
@@ -52,12 +54,55 @@ impl Uid {
     pub fn lot_num(&self) -> &str {
         unsafe { from_utf8_unchecked(&self.waf_lot[1..]) }
     }
+
+    /// The 96 bits of the ID as three words, in register order
+    pub fn words(&self) -> [u32; 3] {
+        let w = &self.waf_lot;
+        [
+            u32::from(self.x) | u32::from(self.y) << 16,
+            u32::from_le_bytes([w[0], w[1], w[2], w[3]]),
+            u32::from_le_bytes([w[4], w[5], w[6], w[7]]),
+        ]
+    }
+
+    /// Writes the ID as 24 uppercase hex digits to `buf`, for use as USB serial number
+    ///
+    /// The words are written in register order, each with the most significant digit first.
+    pub fn serial_number<'a>(&self, buf: &'a mut [u8; 24]) -> &'a str {
+        const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+        for (chunk, word) in buf.chunks_mut(8).zip(self.words().iter()) {
+            for (i, c) in chunk.iter_mut().enumerate() {
+                *c = DIGITS[(word >> (28 - 4 * i) & 0xf) as usize];
+            }
+        }
+        from_utf8(buf).unwrap()
+    }
+
+    /// Derives a locally administered unicast MAC address from the ID
+    ///
+    /// The two halves of the ID are XORed together, so the address is the same on every boot
+    /// but not guaranteed to be unique.
+    pub fn mac_address(&self) -> [u8; 6] {
+        let mut bytes = [0; 12];
+        for (chunk, word) in bytes.chunks_mut(4).zip(self.words().iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+
+        let mut mac = [0; 6];
+        for (i, b) in mac.iter_mut().enumerate() {
+            *b = bytes[i] ^ bytes[i + 6];
+        }
+        // Locally administered, unicast
+        mac[0] = (mac[0] & !0b01) | 0b10;
+        mac
+    }
 }
 
 /// Size of integrated flash
 #[derive(Debug)]
 #[repr(C)]
-pub struct FlashSize(u16);
+pub struct FlashSize(pub(crate) u16);
 define_ptr_type!(FlashSize, 0x1FFF_7A22);
 
 impl FlashSize {
@@ -110,3 +155,282 @@ impl VtempCal110 {
         self.0
     }
 }
+
+/// Package data register
+///
+/// The meaning of the package code is device specific, see the reference manual.
+#[cfg(any(
+    feature = "stm32f410",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f446"
+))]
+#[derive(Debug)]
+#[repr(C)]
+pub struct Package(u16);
+#[cfg(any(
+    feature = "stm32f410",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f446"
+))]
+define_ptr_type!(Package, 0x1FFF_7BF0);
+
+#[cfg(any(
+    feature = "stm32f410",
+    feature = "stm32f412",
+    feature = "stm32f413",
+    feature = "stm32f423",
+    feature = "stm32f446"
+))]
+impl Package {
+    /// Read package code
+    pub fn code(&self) -> u8 {
+        ((self.0 >> 8) & 0b111) as u8
+    }
+}
+
+/// Device line, from the DEV_ID field of DBGMCU_IDCODE
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Device {
+    /// STM32F405/407/415/417
+    F40x,
+    /// STM32F427/429/437/439
+    F42x,
+    /// STM32F401xB/C
+    F401xBC,
+    /// STM32F401xD/E
+    F401xDE,
+    /// STM32F410
+    F410,
+    /// STM32F411
+    F411,
+    /// STM32F412
+    F412,
+    /// STM32F413/423
+    F413,
+    /// STM32F446
+    F446,
+    /// STM32F469/479
+    F469,
+    Unknown(u16),
+}
+
+impl Device {
+    fn from_dev_id(dev_id: u16) -> Self {
+        match dev_id {
+            0x413 => Device::F40x,
+            0x419 => Device::F42x,
+            0x423 => Device::F401xBC,
+            0x433 => Device::F401xDE,
+            0x458 => Device::F410,
+            0x431 => Device::F411,
+            0x441 => Device::F412,
+            0x463 => Device::F413,
+            0x421 => Device::F446,
+            0x434 => Device::F469,
+            id => Device::Unknown(id),
+        }
+    }
+}
+
+/// Silicon revision, as marked on the package, from the REV_ID field of DBGMCU_IDCODE
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Revision {
+    RevA,
+    RevB,
+    RevC,
+    RevZ,
+    RevY,
+    Rev1,
+    Rev2,
+    Rev3,
+    /// Also marked Y on the STM32F40x
+    Rev4,
+    /// Also marked 6 on the STM32F40x, and B on the STM32F42x
+    Rev5,
+    Unknown(u16),
+}
+
+impl Revision {
+    fn from_ids(device: Device, rev_id: u16) -> Self {
+        match (device, rev_id) {
+            // RM0090, DBGMCU_IDCODE, and ES0182
+            (Device::F40x, 0x1000) => Revision::RevA,
+            (Device::F40x, 0x1001) => Revision::RevZ,
+            (Device::F40x, 0x1003) => Revision::Rev1,
+            (Device::F40x, 0x1007) => Revision::Rev2,
+            (Device::F40x, 0x100F) => Revision::Rev4,
+            (Device::F40x, 0x101F) => Revision::Rev5,
+
+            // RM0090, DBGMCU_IDCODE, and ES0206
+            (Device::F42x, 0x1000) => Revision::RevA,
+            (Device::F42x, 0x1003) => Revision::RevY,
+            (Device::F42x, 0x1007) => Revision::Rev1,
+            (Device::F42x, 0x2001) => Revision::Rev3,
+            (Device::F42x, 0x2003) => Revision::Rev5,
+
+            // RM0368, DBGMCU_IDCODE, the order differs between the two lines
+            (Device::F401xBC, 0x1000) => Revision::RevZ,
+            (Device::F401xBC, 0x1001) => Revision::RevA,
+            (Device::F401xDE, 0x1000) => Revision::RevA,
+            (Device::F401xDE, 0x1001) => Revision::RevZ,
+
+            // RM0401, DBGMCU_IDCODE
+            (Device::F410, 0x1000) => Revision::RevA,
+            (Device::F410, 0x1001) => Revision::RevZ,
+
+            // RM0383, DBGMCU_IDCODE
+            (Device::F411, 0x1000) => Revision::RevA,
+
+            // RM0402, DBGMCU_IDCODE
+            (Device::F412, 0x1001) => Revision::RevZ,
+            (Device::F412, 0x2000) => Revision::RevB,
+            (Device::F412, 0x3000) => Revision::RevC,
+
+            // RM0430, DBGMCU_IDCODE
+            (Device::F413, 0x1000) => Revision::RevA,
+
+            // RM0390, DBGMCU_IDCODE
+            (Device::F446, 0x1000) => Revision::RevA,
+
+            // RM0386, DBGMCU_IDCODE
+            (Device::F469, 0x1000) => Revision::RevA,
+
+            (_, id) => Revision::Unknown(id),
+        }
+    }
+}
+
+/// Device and revision identification
+///
+/// On early revisions of the STM32F40x and STM32F42x, DBGMCU_IDCODE reads as zero unless a
+/// debugger is attached, see the errata sheets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeviceId {
+    idcode: u32,
+}
+
+impl DeviceId {
+    /// Read DBGMCU_IDCODE
+    pub fn read() -> Self {
+        let idcode = unsafe { (*DBGMCU::ptr()).idcode.read().bits() };
+        DeviceId { idcode }
+    }
+
+    /// Raw DEV_ID field
+    pub fn dev_id(&self) -> u16 {
+        (self.idcode & 0xfff) as u16
+    }
+
+    /// Raw REV_ID field
+    pub fn rev_id(&self) -> u16 {
+        (self.idcode >> 16) as u16
+    }
+
+    /// Device line
+    pub fn device(&self) -> Device {
+        Device::from_dev_id(self.dev_id())
+    }
+
+    /// Silicon revision
+    pub fn revision(&self) -> Revision {
+        Revision::from_ids(self.device(), self.rev_id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_id_decoding() {
+        let id = DeviceId {
+            idcode: 0x2001_6419,
+        };
+        assert_eq!(id.dev_id(), 0x419);
+        assert_eq!(id.rev_id(), 0x2001);
+        assert_eq!(id.device(), Device::F42x);
+        assert_eq!(id.revision(), Revision::Rev3);
+
+        let id = DeviceId {
+            idcode: 0x1000_6431,
+        };
+        assert_eq!(id.device(), Device::F411);
+        assert_eq!(id.revision(), Revision::RevA);
+
+        // Read without a debugger on affected revisions
+        let id = DeviceId { idcode: 0 };
+        assert_eq!(id.device(), Device::Unknown(0));
+        assert_eq!(id.revision(), Revision::Unknown(0));
+
+        // IDCODE values read from parts
+        let parts: &[(u32, Device, Revision)] = &[
+            (0x1007_6413, Device::F40x, Revision::Rev2),
+            (0x100F_6413, Device::F40x, Revision::Rev4),
+            (0x1000_6423, Device::F401xBC, Revision::RevZ),
+            (0x1000_6433, Device::F401xDE, Revision::RevA),
+            (0x3000_6441, Device::F412, Revision::RevC),
+            (0x1000_6421, Device::F446, Revision::RevA),
+        ];
+        for &(idcode, device, revision) in parts {
+            let id = DeviceId { idcode };
+            assert_eq!(id.device(), device, "{:#x}", idcode);
+            assert_eq!(id.revision(), revision, "{:#x}", idcode);
+        }
+
+        // Number of revisions in the DBGMCU_IDCODE table of each reference manual
+        let families = [
+            (Device::F40x, 6),
+            (Device::F42x, 5),
+            (Device::F401xBC, 2),
+            (Device::F401xDE, 2),
+            (Device::F410, 2),
+            (Device::F411, 1),
+            (Device::F412, 3),
+            (Device::F413, 1),
+            (Device::F446, 1),
+            (Device::F469, 1),
+            (Device::Unknown(0x123), 0),
+        ];
+        for &(device, count) in families.iter() {
+            let mut known = [None; 8];
+            let mut found = 0;
+            for rev_id in 0..=u16::MAX {
+                match Revision::from_ids(device, rev_id) {
+                    Revision::Unknown(id) => assert_eq!(id, rev_id),
+                    revision => {
+                        // Each revision has a single REV_ID
+                        assert!(!known.contains(&Some(revision)), "{:?}", device);
+                        known[found] = Some(revision);
+                        found += 1;
+                    }
+                }
+            }
+            assert_eq!(found, count, "{:?}", device);
+        }
+
+        assert_eq!(Device::from_dev_id(0x463), Device::F413);
+        assert_eq!(Device::from_dev_id(0x123), Device::Unknown(0x123));
+    }
+
+    #[test]
+    fn uid_helpers() {
+        let uid = Uid {
+            x: 0x0012,
+            y: 0x0034,
+            waf_lot: *b"\x07ABCDEFG",
+        };
+        assert_eq!(uid.words(), [0x0034_0012, 0x4342_4107, 0x4746_4544]);
+
+        let mut buf = [0; 24];
+        assert_eq!(uid.serial_number(&mut buf), "003400124342410747464544");
+
+        let mac = uid.mac_address();
+        // The XORed halves with the locally administered bit set
+        assert_eq!(mac, [0x52, 0x43, 0x70, 0x45, 0x41, 0x06]);
+        assert_eq!(mac[0] & 0b11, 0b10);
+    }
+}
@@ -23,7 +23,7 @@ pub use embedded_hal::spi::{Mode, Phase, Polarity};
     feature = "stm32f469",
     feature = "stm32f479"
 ))]
-use crate::stm32::{spi1, RCC, SPI1, SPI2};
+use crate::stm32::{spi1, SPI1, SPI2};
 
 #[cfg(any(
     feature = "stm32f401",
@@ -347,7 +347,7 @@ use crate::gpio::AF7;
 ))]
 use crate::gpio::{Alternate, AF5, AF6};
 
-use crate::rcc::Clocks;
+use crate::rcc::{BusClock, Clocks, Enable};
 use crate::time::Hertz;
 
 /// SPI error
@@ -716,6 +716,7 @@ pub enum Event {
 pub struct Spi<SPI, PINS> {
     spi: SPI,
     pins: PINS,
+    freq: Hertz,
 }
 
 #[cfg(any(
@@ -742,13 +743,10 @@ impl<PINS> Spi<SPI1, PINS> {
     where
         PINS: Pins<SPI1>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for SPI
-        rcc.apb2enr.modify(|_, w| w.spi1en().set_bit());
+        SPI1::enable();
 
-        Spi { spi, pins }.init(mode, freq, clocks.pclk2())
+        Spi { spi, pins, freq }.init(mode, freq, SPI1::clock(&clocks))
     }
 }
 
@@ -776,13 +774,10 @@ impl<PINS> Spi<SPI2, PINS> {
     where
         PINS: Pins<SPI2>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for SPI
-        rcc.apb1enr.modify(|_, w| w.spi2en().set_bit());
+        SPI2::enable();
 
-        Spi { spi, pins }.init(mode, freq, clocks.pclk1())
+        Spi { spi, pins, freq }.init(mode, freq, SPI2::clock(&clocks))
     }
 }
 
@@ -809,13 +804,10 @@ impl<PINS> Spi<SPI3, PINS> {
     where
         PINS: Pins<SPI3>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for SPI
-        rcc.apb1enr.modify(|_, w| w.spi3en().set_bit());
+        SPI3::enable();
 
-        Spi { spi, pins }.init(mode, freq, clocks.pclk1())
+        Spi { spi, pins, freq }.init(mode, freq, SPI3::clock(&clocks))
     }
 }
 
@@ -838,13 +830,10 @@ impl<PINS> Spi<SPI4, PINS> {
     where
         PINS: Pins<SPI4>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for SPI
-        rcc.apb2enr.modify(|_, w| w.spi4en().set_bit());
+        SPI4::enable();
 
-        Spi { spi, pins }.init(mode, freq, clocks.pclk2())
+        Spi { spi, pins, freq }.init(mode, freq, SPI4::clock(&clocks))
     }
 }
 
@@ -866,13 +855,10 @@ impl<PINS> Spi<SPI5, PINS> {
     where
         PINS: Pins<SPI5>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for SPI
-        rcc.apb2enr.modify(|_, w| w.spi5en().set_bit());
+        SPI5::enable();
 
-        Spi { spi, pins }.init(mode, freq, clocks.pclk2())
+        Spi { spi, pins, freq }.init(mode, freq, SPI5::clock(&clocks))
     }
 }
 
@@ -889,13 +875,10 @@ impl<PINS> Spi<SPI6, PINS> {
     where
         PINS: Pins<SPI6>,
     {
-        // NOTE(unsafe) This executes only during initialisation
-        let rcc = unsafe { &(*RCC::ptr()) };
-
         // Enable clock for SPI
-        rcc.apb2enr.modify(|_, w| w.spi6en().set_bit());
+        SPI6::enable();
 
-        Spi { spi, pins }.init(mode, freq, clocks.pclk2())
+        Spi { spi, pins, freq }.init(mode, freq, SPI6::clock(&clocks))
     }
 }
 
@@ -903,21 +886,13 @@ impl<SPI, PINS> Spi<SPI, PINS>
 where
     SPI: Deref<Target = spi1::RegisterBlock>,
 {
-    pub fn init(self, mode: Mode, freq: Hertz, clock: Hertz) -> Self {
+    pub fn init(mut self, mode: Mode, freq: Hertz, clock: Hertz) -> Self {
+        self.freq = freq;
+
         // disable SS output
         self.spi.cr2.write(|w| w.ssoe().clear_bit());
 
-        let br = match clock.0 / freq.0 {
-            0 => unreachable!(),
-            1..=2 => 0b000,
-            3..=5 => 0b001,
-            6..=11 => 0b010,
-            12..=23 => 0b011,
-            24..=47 => 0b100,
-            48..=95 => 0b101,
-            96..=191 => 0b110,
-            _ => 0b111,
-        };
+        let br = Self::br(freq, clock);
 
         // mstr: master configuration
         // lsbfirst: MSB first
@@ -954,6 +929,40 @@ where
         self
     }
 
+    fn br(freq: Hertz, clock: Hertz) -> u8 {
+        match clock.0 / freq.0 {
+            0 => unreachable!(),
+            1..=2 => 0b000,
+            3..=5 => 0b001,
+            6..=11 => 0b010,
+            12..=23 => 0b011,
+            24..=47 => 0b100,
+            48..=95 => 0b101,
+            96..=191 => 0b110,
+            _ => 0b111,
+        }
+    }
+
+    /// Recomputes the baud rate prescaler after the clocks have been reconfigured with
+    /// [`CFGR::reconfigure`](../rcc/struct.CFGR.html#method.reconfigure).
+    ///
+    /// Must not be called while a transfer is ongoing.
+    pub fn reclock(&mut self, clocks: &Clocks)
+    where
+        SPI: BusClock,
+    {
+        self.set_baud_rate(SPI::clock(clocks));
+    }
+
+    fn set_baud_rate(&mut self, clock: Hertz) {
+        let br = Self::br(self.freq, clock);
+
+        // The baud rate can only be changed while the SPI is disabled
+        self.spi.cr1.modify(|_, w| w.spe().clear_bit());
+        self.spi.cr1.modify(|_, w| w.br().bits(br));
+        self.spi.cr1.modify(|_, w| w.spe().set_bit());
+    }
+
     /// Enable interrupts for the given `event`:
     ///  - Received data ready to be read (RXNE)
     ///  - Transmit data register empty (TXE)
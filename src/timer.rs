@@ -6,7 +6,6 @@ use cortex_m::peripheral::SYST;
 use embedded_hal::timer::{Cancel, CountDown, Periodic};
 use void::Void;
 
-use crate::stm32::RCC;
 #[cfg(any(
     feature = "stm32f405",
     feature = "stm32f407",
@@ -82,13 +81,14 @@ use crate::stm32::{TIM10, TIM2, TIM3, TIM4};
 ))]
 use crate::stm32::{TIM12, TIM13, TIM14, TIM7, TIM8};
 
-use crate::rcc::Clocks;
+use crate::rcc::{BusTimerClock, Clocks, Enable, Reset};
 use crate::time::Hertz;
 
 /// Hardware timers
 pub struct Timer<TIM> {
     clocks: Clocks,
     tim: TIM,
+    timeout: Hertz,
 }
 
 /// Interrupt events
@@ -110,11 +110,33 @@ impl Timer<SYST> {
         T: Into<Hertz>,
     {
         syst.set_clock_source(SystClkSource::Core);
-        let mut timer = Timer { tim: syst, clocks };
+        let timeout = timeout.into();
+        let mut timer = Timer {
+            tim: syst,
+            clocks,
+            timeout,
+        };
         timer.start(timeout);
         timer
     }
 
+    /// Recomputes the reload value after the clocks have been reconfigured with
+    /// [`CFGR::reconfigure`](../rcc/struct.CFGR.html#method.reconfigure), restarting the current
+    /// period.
+    pub fn reclock(&mut self, clocks: &Clocks) {
+        self.clocks = *clocks;
+        self.set_reload();
+        self.tim.clear_current();
+    }
+
+    fn set_reload(&mut self) {
+        let rvr = self.clocks.sysclk().0 / self.timeout.0 - 1;
+
+        assert!(rvr < (1 << 24));
+
+        self.tim.set_reload(rvr);
+    }
+
     /// Starts listening for an `event`
     pub fn listen(&mut self, event: Event) {
         match event {
@@ -137,11 +159,8 @@ impl CountDown for Timer<SYST> {
     where
         T: Into<Hertz>,
     {
-        let rvr = self.clocks.sysclk().0 / timeout.into().0 - 1;
-
-        assert!(rvr < (1 << 24));
-
-        self.tim.set_reload(rvr);
+        self.timeout = timeout.into();
+        self.set_reload();
         self.tim.clear_current();
         self.tim.enable_counter();
     }
@@ -171,7 +190,7 @@ impl Cancel for Timer<SYST> {
 impl Periodic for Timer<SYST> {}
 
 macro_rules! hal {
-    ($($TIM:ident: ($tim:ident, $timXen:ident, $timXrst:ident, $apbenr:ident, $apbrstr:ident, $pclk:ident, $ppre:ident),)+) => {
+    ($($TIM:ident: $tim:ident,)+) => {
         $(
             impl Timer<$TIM> {
                 /// Configures a TIM peripheral as a periodic count down timer
@@ -180,14 +199,14 @@ macro_rules! hal {
                     T: Into<Hertz>,
                 {
                     // enable and reset peripheral to a clean slate state
-                    let rcc = unsafe { &(*RCC::ptr()) };
-                    rcc.$apbenr.modify(|_, w| w.$timXen().set_bit());
-                    rcc.$apbrstr.modify(|_, w| w.$timXrst().set_bit());
-                    rcc.$apbrstr.modify(|_, w| w.$timXrst().clear_bit());
+                    $TIM::enable();
+                    $TIM::reset();
 
+                    let timeout = timeout.into();
                     let mut timer = Timer {
                         clocks,
                         tim,
+                        timeout,
                     };
                     timer.start(timeout);
 
@@ -230,6 +249,39 @@ macro_rules! hal {
                     }
                 }
 
+                /// Recomputes the prescaler and auto-reload value after the clocks have been
+                /// reconfigured with [`CFGR::reconfigure`](../rcc/struct.CFGR.html#method.reconfigure),
+                /// restarting the current period.
+                pub fn reclock(&mut self, clocks: &Clocks) {
+                    self.clocks = *clocks;
+
+                    let enabled = self.tim.cr1.read().cen().is_enabled();
+                    // pause
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    // reset counter
+                    self.tim.cnt.reset();
+
+                    self.set_period();
+
+                    self.tim.cr1.modify(|_, w| w.cen().bit(enabled));
+                }
+
+                fn set_period(&mut self) {
+                    let frequency = self.timeout.0;
+                    let ticks = $TIM::timer_clock(&self.clocks).0 / frequency;
+
+                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
+                    self.tim.psc.write(|w| w.psc().bits(psc) );
+
+                    let arr = u16(ticks / u32(psc + 1)).unwrap();
+                    self.tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+
+                    // Trigger update event to load the prescaler, without setting the update flag
+                    self.tim.cr1.modify(|_, w| w.urs().set_bit());
+                    self.tim.egr.write(|w| w.ug().set_bit());
+                    self.tim.cr1.modify(|_, w| w.urs().clear_bit());
+                }
+
                 /// Releases the TIM peripheral
                 pub fn release(self) -> $TIM {
                     // pause counter
@@ -250,15 +302,8 @@ macro_rules! hal {
                     // reset counter
                     self.tim.cnt.reset();
 
-                    let frequency = timeout.into().0;
-                    let pclk_mul = if self.clocks.$ppre() == 1 { 1 } else { 2 };
-                    let ticks = self.clocks.$pclk().0 * pclk_mul / frequency;
-
-                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-                    self.tim.psc.write(|w| w.psc().bits(psc) );
-
-                    let arr = u16(ticks / u32(psc + 1)).unwrap();
-                    self.tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
+                    self.timeout = timeout.into();
+                    self.set_period();
 
                     // start counter
                     self.tim.cr1.modify(|_, w| w.cen().set_bit());
@@ -315,10 +360,10 @@ macro_rules! hal {
     feature = "stm32f479"
 ))]
 hal! {
-    TIM1: (tim1, tim1en, tim1rst, apb2enr, apb2rstr, pclk2, ppre2),
-    TIM5: (tim5, tim5en, tim5rst, apb1enr, apb1rstr, pclk1, ppre1),
-    TIM9: (tim9, tim9en, tim9rst, apb2enr, apb2rstr, pclk2, ppre2),
-    TIM11: (tim11, tim11en, tim11rst, apb2enr, apb2rstr, pclk2, ppre2),
+    TIM1: tim1,
+    TIM5: tim5,
+    TIM9: tim9,
+    TIM11: tim11,
 }
 
 #[cfg(any(
@@ -340,10 +385,10 @@ hal! {
     feature = "stm32f479"
 ))]
 hal! {
-    TIM2: (tim2, tim2en, tim2rst, apb1enr, apb1rstr, pclk1, ppre1),
-    TIM3: (tim3, tim3en, tim3rst, apb1enr, apb1rstr, pclk1, ppre1),
-    TIM4: (tim4, tim4en, tim4rst, apb1enr, apb1rstr, pclk1, ppre1),
-    TIM10: (tim10, tim10en, tim10rst, apb2enr, apb2rstr, pclk2, ppre2),
+    TIM2: tim2,
+    TIM3: tim3,
+    TIM4: tim4,
+    TIM10: tim10,
 }
 
 #[cfg(any(
@@ -364,7 +409,7 @@ hal! {
     feature = "stm32f479"
 ))]
 hal! {
-    TIM6: (tim6, tim6en, tim6rst, apb1enr, apb1rstr, pclk1, ppre1),
+    TIM6: tim6,
 }
 
 #[cfg(any(
@@ -384,11 +429,11 @@ hal! {
     feature = "stm32f479"
 ))]
 hal! {
-    TIM7: (tim7, tim7en, tim7rst, apb1enr, apb1rstr, pclk1, ppre1),
-    TIM8: (tim8, tim8en, tim8rst, apb2enr, apb2rstr, pclk2, ppre2),
-    TIM12: (tim12, tim12en, tim12rst, apb1enr, apb1rstr, pclk1, ppre1),
-    TIM13: (tim13, tim13en, tim13rst, apb1enr, apb1rstr, pclk1, ppre1),
-    TIM14: (tim14, tim14en, tim14rst, apb1enr, apb1rstr, pclk1, ppre1),
+    TIM7: tim7,
+    TIM8: tim8,
+    TIM12: tim12,
+    TIM13: tim13,
+    TIM14: tim14,
 }
 
 #[cfg(any(
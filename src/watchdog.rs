@@ -2,8 +2,9 @@
 
 use crate::{
     hal::watchdog::{Watchdog, WatchdogEnable},
-    stm32::{DBGMCU, IWDG},
-    time::MilliSeconds,
+    rcc::{Clocks, Enable, Reset},
+    stm32::{DBGMCU, IWDG, WWDG},
+    time::{MicroSeconds, MilliSeconds},
 };
 
 /// Wraps the Independent Watchdog (IWDG) peripheral
@@ -102,3 +103,130 @@ impl Watchdog for IndependentWatchdog {
         self.iwdg.kr.write(|w| unsafe { w.key().bits(KR_RELOAD) });
     }
 }
+
+/// Wraps the Window Watchdog (WWDG) peripheral
+///
+/// The watchdog resets the device if it is not fed before the timeout, or if it is fed too early,
+/// before the window opens. Once started, it can only be stopped by a reset.
+///
+/// The longest timeout is 64 ticks of PCLK1 / 32768, about 49.9 ms with PCLK1 at 42 MHz,
+/// 46.6 ms at 45 MHz, 41.9 ms at 50 MHz and 131 ms at 16 MHz. `start` panics if the
+/// requested period is longer.
+pub struct WindowWatchdog {
+    wwdg: WWDG,
+    pclk1: u32,
+    window: u32,
+    /// Counter value written on start and feed
+    counter: u8,
+}
+
+/// Window watchdog interrupt events
+pub enum Event {
+    /// The counter reached 0x40, one tick before the reset
+    EarlyWakeup,
+}
+
+const WWDG_COUNTER_MIN: u8 = 0x40;
+const WWDG_COUNTER_MAX: u8 = 0x7F;
+const MAX_WDGTB: u8 = 0b11;
+
+impl WindowWatchdog {
+    /// Wraps the watchdog, the timing is derived from the PCLK1 frequency in `clocks`
+    pub fn new(wwdg: WWDG, clocks: &Clocks) -> Self {
+        WWDG::enable();
+        WWDG::reset();
+
+        WindowWatchdog {
+            wwdg,
+            pclk1: clocks.pclk1().0,
+            window: 0,
+            counter: WWDG_COUNTER_MAX,
+        }
+    }
+
+    /// Debug window watchdog stopped when core is halted
+    pub fn stop_on_debug(&self, dbgmcu: &DBGMCU, stop: bool) {
+        dbgmcu.apb1_fz.modify(|_, w| w.dbg_wwdg_stop().bit(stop));
+    }
+
+    /// Sets the time after a feed during which the next feed causes a reset, applied on `start`
+    pub fn set_window<T: Into<MicroSeconds>>(&mut self, window: T) {
+        self.window = window.into().0;
+    }
+
+    /// Starts listening for an `event`, the interrupt can only be disabled by a reset
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::EarlyWakeup => self.wwdg.cfr.modify(|_, w| w.ewi().set_bit()),
+        }
+    }
+
+    /// Clears interrupt associated with `event`
+    pub fn clear_interrupt(&mut self, event: Event) {
+        match event {
+            Event::EarlyWakeup => self.wwdg.sr.write(|w| w.ewif().clear_bit()),
+        }
+    }
+
+    /// Returns `true` if `event` occurred
+    pub fn is_pending(&self, event: Event) -> bool {
+        match event {
+            Event::EarlyWakeup => self.wwdg.sr.read().ewif().bit_is_set(),
+        }
+    }
+
+    /// Returns the time from a feed to the reset
+    pub fn timeout(&self) -> MicroSeconds {
+        let wdgtb = self.wwdg.cfr.read().wdgtb().bits();
+        MicroSeconds(self.ticks_to_us(wdgtb, u32::from(self.counter - WWDG_COUNTER_MIN) + 1))
+    }
+
+    fn setup(&mut self, timeout_us: u32) {
+        assert!(
+            timeout_us <= self.ticks_to_us(MAX_WDGTB, 64),
+            "WWDG timeout too long for PCLK1"
+        );
+
+        let mut wdgtb = 0;
+        while wdgtb < MAX_WDGTB && self.ticks_to_us(wdgtb, 64) < timeout_us {
+            wdgtb += 1;
+        }
+
+        let ticks =
+            |us: u32| (u64::from(us) * u64::from(self.pclk1) / (4_096_000_000 << wdgtb)) as u32;
+        let timeout_ticks = ticks(timeout_us).max(1);
+        let counter = WWDG_COUNTER_MIN - 1 + timeout_ticks as u8;
+        // Feeding is only allowed once the counter went below the window value
+        let window = counter
+            .saturating_sub(ticks(self.window).min(63) as u8)
+            .max(WWDG_COUNTER_MIN);
+
+        self.counter = counter;
+        self.wwdg
+            .cfr
+            .modify(|_, w| w.wdgtb().bits(wdgtb).w().bits(window));
+    }
+
+    /// Duration of `ticks` counter decrements with the `wdgtb` prescaler, in us
+    fn ticks_to_us(&self, wdgtb: u8, ticks: u32) -> u32 {
+        (u64::from(ticks) * (4_096_000_000 << wdgtb) / u64::from(self.pclk1)) as u32
+    }
+}
+
+impl WatchdogEnable for WindowWatchdog {
+    type Time = MicroSeconds;
+
+    fn start<T: Into<Self::Time>>(&mut self, period: T) {
+        self.setup(period.into().0);
+
+        let counter = self.counter;
+        self.wwdg.cr.write(|w| w.wdga().set_bit().t().bits(counter));
+    }
+}
+
+impl Watchdog for WindowWatchdog {
+    fn feed(&mut self) {
+        let counter = self.counter;
+        self.wwdg.cr.write(|w| w.wdga().set_bit().t().bits(counter));
+    }
+}